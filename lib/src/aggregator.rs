@@ -1,66 +1,523 @@
+use chrono::{DateTime, Utc};
 use std::collections::HashMap;
 use tracing::instrument;
 use url::Url;
 
-use crate::SearchResult;
+use crate::{
+    canonical::{canonical_image_key, canonical_key, is_better_display_url},
+    domain_list::DomainList,
+    host_rules::{HostAction, HostRule, HostRules},
+    near_duplicate::Fingerprint,
+    ranking::Ranking,
+    ImageResult, NewsResult, Relavancy, SearchResult, VideoResult,
+};
+
+/// Results which can be deduplicated and ranked by the [`Aggregator`].
+pub trait Rankable {
+    /// Results with the same key are considered duplicates, the key of results linking to a page is
+    /// its canonical url.
+    fn dedup_key(&self) -> String;
+    /// Url of the page the result links to, this is matched against the domain lists.
+    fn page_url(&self) -> &Url;
+    fn page_url_mut(&mut self) -> &mut Url;
+    fn title(&self) -> &str;
+    fn score(&self) -> f32;
+    fn score_mut(&mut self) -> &mut f32;
+    fn sources(&self) -> &[String];
+    /// Host rules which matched the result.
+    fn host_rules(&self) -> &[HostRule];
+    fn host_rules_mut(&mut self) -> &mut Vec<HostRule>;
+    /// Merges a duplicate result suggested by another engine into this one.
+    fn merge(&mut self, duplicate: Self);
+    /// Publication date, for results which have one.
+    fn published_at(&self) -> Option<DateTime<Utc>> {
+        None
+    }
+}
+
+/// Results which can be collapsed with the results linking to other copies of their page.
+pub trait Collapsible: Rankable {
+    /// Compared along with the title to find copies of the page.
+    fn description(&self) -> &str;
+    /// Lists a near-duplicate result as an alternate of this one.
+    fn add_alternate(&mut self, duplicate: Self);
+}
+
+impl Rankable for SearchResult {
+    fn dedup_key(&self) -> String {
+        canonical_key(&self.url)
+    }
+
+    fn page_url(&self) -> &Url {
+        &self.url
+    }
+
+    fn page_url_mut(&mut self) -> &mut Url {
+        &mut self.url
+    }
+
+    fn title(&self) -> &str {
+        &self.title
+    }
+
+    fn score(&self) -> f32 {
+        self.score
+    }
+
+    fn score_mut(&mut self) -> &mut f32 {
+        &mut self.score
+    }
+
+    fn sources(&self) -> &[String] {
+        &self.sources
+    }
+
+    fn host_rules(&self) -> &[HostRule] {
+        &self.host_rules
+    }
+
+    fn host_rules_mut(&mut self) -> &mut Vec<HostRule> {
+        &mut self.host_rules
+    }
+
+    fn merge(&mut self, duplicate: Self) {
+        if is_better_display_url(&duplicate.url, &self.url) {
+            self.url = duplicate.url;
+        }
+        merge_text(&mut self.title, duplicate.title);
+        merge_text(&mut self.description, duplicate.description);
+        self.sources.extend(duplicate.sources);
+        self.snippets.extend(duplicate.snippets);
+    }
+}
+
+impl Collapsible for SearchResult {
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn add_alternate(&mut self, duplicate: Self) {
+        self.alternates.push(duplicate.url);
+        self.alternates.extend(duplicate.alternates);
+        add_sources(&mut self.sources, duplicate.sources);
+    }
+}
+
+impl Rankable for ImageResult {
+    /// Image hosts often add resizing parameters to the query, those are ignored.
+    fn dedup_key(&self) -> String {
+        canonical_image_key(&self.url)
+    }
+
+    fn page_url(&self) -> &Url {
+        &self.page_url
+    }
+
+    fn page_url_mut(&mut self) -> &mut Url {
+        &mut self.page_url
+    }
+
+    fn title(&self) -> &str {
+        &self.title
+    }
+
+    fn score(&self) -> f32 {
+        self.score
+    }
+
+    fn score_mut(&mut self) -> &mut f32 {
+        &mut self.score
+    }
+
+    fn sources(&self) -> &[String] {
+        &self.sources
+    }
+
+    fn host_rules(&self) -> &[HostRule] {
+        &self.host_rules
+    }
+
+    fn host_rules_mut(&mut self) -> &mut Vec<HostRule> {
+        &mut self.host_rules
+    }
+
+    fn merge(&mut self, duplicate: Self) {
+        // Not every engine reports the dimensions of the image
+        if self.width.is_none() || self.height.is_none() {
+            self.width = duplicate.width;
+            self.height = duplicate.height;
+        }
+        merge_text(&mut self.title, duplicate.title);
+        self.sources.extend(duplicate.sources);
+    }
+}
+
+impl Rankable for NewsResult {
+    fn dedup_key(&self) -> String {
+        canonical_key(&self.url)
+    }
+
+    fn page_url(&self) -> &Url {
+        &self.url
+    }
+
+    fn page_url_mut(&mut self) -> &mut Url {
+        &mut self.url
+    }
+
+    fn title(&self) -> &str {
+        &self.title
+    }
+
+    fn score(&self) -> f32 {
+        self.score
+    }
+
+    fn score_mut(&mut self) -> &mut f32 {
+        &mut self.score
+    }
+
+    fn sources(&self) -> &[String] {
+        &self.sources
+    }
+
+    fn host_rules(&self) -> &[HostRule] {
+        &self.host_rules
+    }
+
+    fn host_rules_mut(&mut self) -> &mut Vec<HostRule> {
+        &mut self.host_rules
+    }
+
+    fn merge(&mut self, duplicate: Self) {
+        if is_better_display_url(&duplicate.url, &self.url) {
+            self.url = duplicate.url;
+        }
+        merge_text(&mut self.title, duplicate.title);
+        merge_text(&mut self.description, duplicate.description);
+        self.publisher = self.publisher.take().or(duplicate.publisher);
+        self.published_at = self.published_at.or(duplicate.published_at);
+        self.thumbnail = self.thumbnail.take().or(duplicate.thumbnail);
+        self.sources.extend(duplicate.sources);
+        self.snippets.extend(duplicate.snippets);
+    }
+
+    fn published_at(&self) -> Option<DateTime<Utc>> {
+        self.published_at
+    }
+}
+
+impl Collapsible for NewsResult {
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn add_alternate(&mut self, duplicate: Self) {
+        self.alternates.push(duplicate.url);
+        self.alternates.extend(duplicate.alternates);
+        add_sources(&mut self.sources, duplicate.sources);
+    }
+}
+
+impl Rankable for VideoResult {
+    fn dedup_key(&self) -> String {
+        canonical_key(&self.url)
+    }
+
+    fn page_url(&self) -> &Url {
+        &self.url
+    }
+
+    fn page_url_mut(&mut self) -> &mut Url {
+        &mut self.url
+    }
+
+    fn title(&self) -> &str {
+        &self.title
+    }
+
+    fn score(&self) -> f32 {
+        self.score
+    }
+
+    fn score_mut(&mut self) -> &mut f32 {
+        &mut self.score
+    }
+
+    fn sources(&self) -> &[String] {
+        &self.sources
+    }
+
+    fn host_rules(&self) -> &[HostRule] {
+        &self.host_rules
+    }
+
+    fn host_rules_mut(&mut self) -> &mut Vec<HostRule> {
+        &mut self.host_rules
+    }
+
+    fn merge(&mut self, duplicate: Self) {
+        if is_better_display_url(&duplicate.url, &self.url) {
+            self.url = duplicate.url;
+        }
+        merge_text(&mut self.title, duplicate.title);
+        merge_text(&mut self.description, duplicate.description);
+        self.thumbnail = self.thumbnail.take().or(duplicate.thumbnail);
+        self.duration = self.duration.or(duplicate.duration);
+        self.channel = self.channel.take().or(duplicate.channel);
+        self.published_at = self.published_at.or(duplicate.published_at);
+        self.sources.extend(duplicate.sources);
+        self.snippets.extend(duplicate.snippets);
+    }
+
+    fn published_at(&self) -> Option<DateTime<Utc>> {
+        self.published_at
+    }
+}
+
+impl Collapsible for VideoResult {
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn add_alternate(&mut self, duplicate: Self) {
+        self.alternates.push(duplicate.url);
+        self.alternates.extend(duplicate.alternates);
+        add_sources(&mut self.sources, duplicate.sources);
+    }
+}
+
+/// Adds the engines which aren't listed yet, an engine can suggest several copies of a page.
+fn add_sources(sources: &mut Vec<String>, duplicates: Vec<String>) {
+    for source in duplicates {
+        if !sources.contains(&source) {
+            sources.push(source);
+        }
+    }
+}
+
+/// Replaces `current` with the text of a duplicate when it's more informative.
+///
+/// Engines often cut titles and descriptions short with an ellipsis, so complete text is
+/// preferred over truncated text, then the longer one.
+fn merge_text(current: &mut String, candidate: String) {
+    let rank = |text: &str| {
+        let text = text.trim();
+        let truncated = text.ends_with("...") || text.ends_with('…');
+        (!text.is_empty(), !truncated, text.chars().count())
+    };
+
+    if rank(&candidate) > rank(current) {
+        *current = candidate;
+    }
+}
 
 #[derive(Debug)]
 pub struct Aggregator {
+    ranking: Box<dyn Ranking>,
     score_multipliers: HashMap<String, f32>,
+    // Results matching this list are pinned above the rest
+    allow_list: DomainList,
+    // Results matching this list are dropped
+    block_list: DomainList,
+    collapse_near_duplicates: bool,
+    host_rules: HostRules,
 }
 
 /// Handles the filtering, scoring and sorting of results
 ///
 /// The scores are calculated by summing the scores given by each search engine.
-/// The scores given by each engine = position of result from last * score multiplier of search engine
+/// The scores given by each engine = score of the rank of the result * score multiplier of search engine
+///                                   * factors of the matching host rules
 /// The scoring is done on the assumption that results are parsed in the right order
 impl Aggregator {
-    pub fn new(score_multipliers: HashMap<String, f32>) -> Self {
-        Aggregator { score_multipliers }
+    pub fn new(
+        ranking: Box<dyn Ranking>,
+        score_multipliers: HashMap<String, f32>,
+        allow_list: DomainList,
+        block_list: DomainList,
+        collapse_near_duplicates: bool,
+        host_rules: HostRules,
+    ) -> Self {
+        Aggregator {
+            ranking,
+            score_multipliers,
+            allow_list,
+            block_list,
+            collapse_near_duplicates,
+            host_rules,
+        }
     }
 
     /// Deduplicate the search results and rank it based on its position and no of occurences
+    ///
+    /// Returns the ranked results along with the number of results dropped by the block list and
+    /// the number of results hidden by host rules.
     #[instrument(level = "TRACE", skip_all)]
-    pub fn process(&self, raw_results: Vec<Vec<SearchResult>>) -> Vec<SearchResult> {
+    pub fn process<T: Rankable>(&self, raw_results: Vec<Vec<T>>) -> (Vec<T>, usize, usize) {
         // Please send a pull request if you have a better way to do this!
 
-        let mut deduped_results: HashMap<Url, SearchResult> = HashMap::new();
+        let mut deduped_results: HashMap<String, T> = HashMap::new();
+        let longest = raw_results.iter().map(Vec::len).max().unwrap_or_default();
 
         for results in raw_results {
-            let total_results = results.len() as f32;
+            let count = results.len();
+
+            for (rank, mut result) in results.into_iter().enumerate() {
+                // The url is only rewritten once the results are filtered, see `apply_rewrites`
+                *result.host_rules_mut() = self.host_rules.matching(result.page_url());
+
+                let score = self.score_result(&result, rank, count, longest);
 
-            for (pos, mut result) in results.into_iter().rev().enumerate() {
-                match deduped_results.get_mut(&result.url) {
+                match deduped_results.get_mut(&result.dedup_key()) {
                     Some(existing_result) => {
-                        tracing::debug!("Found duplicate result: {}", existing_result.url);
+                        tracing::debug!("Found duplicate result: {}", existing_result.page_url());
 
-                        existing_result.score +=
-                            self.score_result(&result, (pos + 1) as f32, total_results);
-                        existing_result.sources.extend(result.sources);
+                        *existing_result.score_mut() += score;
+                        existing_result.merge(result);
                     }
                     None => {
-                        result.score = self.score_result(&result, (pos + 1) as f32, total_results);
-                        deduped_results.insert(result.url.clone(), result);
+                        *result.score_mut() = score;
+                        deduped_results.insert(result.dedup_key(), result);
                     }
                 };
             }
         }
 
-        let mut agg_results: Vec<SearchResult> = deduped_results.into_values().collect();
+        let mut agg_results: Vec<T> = deduped_results.into_values().collect();
+
+        let total_results = agg_results.len();
+        agg_results.retain(|result| {
+            let blocked = self.block_list.is_match(result.page_url());
+            if blocked {
+                tracing::debug!("Result {} dropped by the block list", result.page_url());
+            }
+            !blocked
+        });
+        let blocked_results = total_results - agg_results.len();
+        if blocked_results > 0 {
+            tracing::info!("Block list dropped {blocked_results} results");
+        }
+
+        let total_results = agg_results.len();
+        agg_results.retain(|result| {
+            let hidden = result
+                .host_rules()
+                .iter()
+                .any(|rule| matches!(rule.action, HostAction::Hide));
+            if hidden {
+                tracing::debug!("Result {} hidden by a host rule", result.page_url());
+            }
+            !hidden
+        });
+        let hidden_results = total_results - agg_results.len();
+
+        let (mut pinned_results, mut agg_results): (Vec<T>, Vec<T>) = agg_results
+            .into_iter()
+            .partition(|result| self.allow_list.is_match(result.page_url()));
+
         // sort in descending order
-        agg_results.sort_by(|b, a| a.score.partial_cmp(&b.score).unwrap());
+        pinned_results.sort_by(|b, a| a.score().partial_cmp(&b.score()).unwrap());
+        agg_results.sort_by(|b, a| a.score().partial_cmp(&b.score()).unwrap());
+
+        // Results from the allow list are always placed above the rest
+        pinned_results.extend(agg_results);
+
+        (pinned_results, blocked_results, hidden_results)
+    }
 
-        agg_results
+    /// Rewrites the hosts of the results matching rewrite rules.
+    ///
+    /// This is done last, so that the domain lists are matched against the url the engine
+    /// returned.
+    pub fn apply_rewrites<T: Rankable>(&self, results: &mut [T]) {
+        for result in results {
+            let rules = result.host_rules().to_vec();
+            HostRules::rewrite(result.page_url_mut(), &rules);
+        }
+    }
+
+    /// Collapses the results linking to copies of the same page, like its AMP or mobile version,
+    /// mirrors and syndicated articles, into the highest ranked one when enabled.
+    ///
+    /// The original page is shown in place of its AMP or mobile version, the copies are listed as
+    /// alternates.
+    pub fn collapse_near_duplicates<T: Collapsible>(&self, results: &mut Vec<T>) {
+        if !self.collapse_near_duplicates {
+            return;
+        }
+
+        let mut collapsed: Vec<(T, Fingerprint)> = Vec::with_capacity(results.len());
+
+        for mut result in results.drain(..) {
+            let mut fingerprint =
+                Fingerprint::new(result.page_url(), result.title(), result.description());
+
+            match collapsed
+                .iter_mut()
+                .find(|(_, kept)| kept.is_near_duplicate(&fingerprint))
+            {
+                Some((kept, kept_fingerprint)) => {
+                    tracing::debug!(
+                        "Collapsing near duplicate {} into {}",
+                        result.page_url(),
+                        kept.page_url()
+                    );
+
+                    if kept_fingerprint.is_variant() && !fingerprint.is_variant() {
+                        std::mem::swap(kept, &mut result);
+                        std::mem::swap(kept_fingerprint, &mut fingerprint);
+                    }
+                    kept.add_alternate(result);
+                }
+                None => collapsed.push((result, fingerprint)),
+            }
+        }
+
+        results.extend(collapsed.into_iter().map(|(result, _)| result));
+    }
+
+    /// Drops the results published before the relavancy window. Results without a publication
+    /// date are kept as the engines have already been asked to filter them.
+    pub fn filter_by_relavancy<T: Rankable>(&self, results: &mut Vec<T>, relavancy: Relavancy) {
+        if let Some(max_age) = relavancy.max_age() {
+            let oldest = Utc::now() - max_age;
+            results.retain(|result| {
+                result
+                    .published_at()
+                    .map(|published_at| published_at >= oldest)
+                    .unwrap_or(true)
+            });
+        }
+    }
+
+    /// Sorts the results with the newest first, results without a publication date are placed
+    /// last in the order of their score.
+    pub fn sort_by_recency<T: Rankable>(&self, results: &mut [T]) {
+        // The sort is stable, so results with the same date stay in the order of their score
+        results.sort_by_key(|result| std::cmp::Reverse(result.published_at()));
     }
 
     #[inline]
-    fn score_result(&self, result: &SearchResult, pos: f32, total_results: f32) -> f32 {
+    fn score_result(
+        &self,
+        result: &impl Rankable,
+        rank: usize,
+        count: usize,
+        longest: usize,
+    ) -> f32 {
         // The search result is guaranteed to have at least one element in the source field.
         let score_multiplier = self
             .score_multipliers
-            .get(result.sources.last().unwrap())
+            .get(result.sources().last().unwrap())
             .unwrap_or(&1.0);
 
-        score_multiplier * (pos / total_results) as f32
+        let host_factor: f32 = result
+            .host_rules()
+            .iter()
+            .map(|rule| rule.action.score_factor())
+            .product();
+
+        score_multiplier * host_factor * self.ranking.score(rank, count, longest)
     }
 }
@@ -0,0 +1,277 @@
+use url::Url;
+
+/// Query parameters which only track where a visitor came from and don't change the page.
+const TRACKING_PARAMS: &[&str] = &[
+    "fbclid",
+    "gclid",
+    "dclid",
+    "gbraid",
+    "wbraid",
+    "msclkid",
+    "yclid",
+    "twclid",
+    "igshid",
+    "mc_cid",
+    "mc_eid",
+    "_ga",
+    "_gl",
+    "_hsenc",
+    "_hsmi",
+    "mkt_tok",
+    "oly_anon_id",
+    "oly_enc_id",
+    "vero_id",
+    "wickedid",
+    "__s",
+];
+/// Prefixes of families of tracking parameters, like `utm_source` and `utm_medium`.
+const TRACKING_PREFIXES: &[&str] = &["utm_", "pk_", "ga_"];
+/// Query parameters which image hosts and CDNs use to resize or reencode the same image.
+const RESIZE_PARAMS: &[&str] = &[
+    "w", "h", "width", "height", "size", "resize", "fit", "crop", "quality", "dpr", "fm", "format",
+    "auto",
+];
+
+/// Normalizes a url so that the different ways of writing the same address map to the same key.
+///
+/// - http and https are treated the same, as are the default ports of either.
+/// - The host is lowercased and a leading `www.` is dropped.
+/// - Trailing slashes and the fragment are dropped.
+/// - Tracking parameters are dropped and the remaining ones are sorted.
+/// - Percent-encoded unreserved characters are decoded and the other escapes are uppercased.
+///
+/// Other schemes only have their path and query normalized.
+pub fn canonical_key(url: &Url) -> String {
+    let web = matches!(url.scheme(), "http" | "https");
+
+    let mut key = if web {
+        String::new()
+    } else {
+        format!("{}:", url.scheme())
+    };
+
+    if let Some(host) = url.host_str() {
+        // The parser already lowercases domains
+        key.push_str(host.strip_prefix("www.").unwrap_or(host));
+    }
+    match url.port() {
+        Some(80 | 443) if web => {}
+        Some(port) => key.push_str(&format!(":{port}")),
+        None => {}
+    }
+
+    key.push_str(&normalize_percent_encoding(
+        url.path().trim_end_matches('/'),
+    ));
+
+    let mut params: Vec<String> = url
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .filter(|param| !param.is_empty() && !is_tracking_param(param))
+        .map(normalize_percent_encoding)
+        .collect();
+    if !params.is_empty() {
+        params.sort_unstable();
+        key.push('?');
+        key.push_str(&params.join("&"));
+    }
+
+    key
+}
+
+/// Canonical key of an image url, which also ignores the parameters resizing the image so that
+/// the thumbnails of an image are seen as the image itself.
+pub fn canonical_image_key(url: &Url) -> String {
+    let mut url = url.clone();
+
+    let query = url
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .filter(|param| {
+            let name = param.split('=').next().unwrap_or_default().to_lowercase();
+            !RESIZE_PARAMS.contains(&name.as_str())
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+    url.set_query((!query.is_empty()).then_some(&query));
+
+    canonical_key(&url)
+}
+
+/// Picks the url shown for a result when duplicates link to different forms of the same page.
+///
+/// Https is preferred over http, then urls without tracking parameters, then the shortest one.
+pub fn is_better_display_url(candidate: &Url, current: &Url) -> bool {
+    let rank = |url: &Url| {
+        let tracking_params = url
+            .query()
+            .unwrap_or_default()
+            .split('&')
+            .filter(|param| is_tracking_param(param))
+            .count();
+
+        (url.scheme() != "https", tracking_params, url.as_str().len())
+    };
+
+    rank(candidate) < rank(current)
+}
+
+fn is_tracking_param(param: &str) -> bool {
+    let name = param.split('=').next().unwrap_or_default().to_lowercase();
+
+    TRACKING_PARAMS.contains(&name.as_str())
+        || TRACKING_PREFIXES
+            .iter()
+            .any(|prefix| name.starts_with(prefix))
+}
+
+/// Decodes escaped unreserved characters (RFC 3986 section 2.3), which are equivalent to the
+/// character itself, and uppercases the hex digits of the other escapes.
+fn normalize_percent_encoding(text: &str) -> String {
+    let mut normalized = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(pos) = rest.find('%') {
+        normalized.push_str(&rest[..pos]);
+        rest = &rest[pos..];
+
+        match rest
+            .get(1..3)
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            Some(byte) if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) => {
+                normalized.push(char::from(byte));
+            }
+            Some(_) => normalized.push_str(&rest[..3].to_uppercase()),
+            // Not an escape
+            None => {
+                normalized.push('%');
+                rest = &rest[1..];
+                continue;
+            }
+        }
+        rest = &rest[3..];
+    }
+    normalized.push_str(rest);
+
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(url: &str) -> String {
+        canonical_key(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn http_and_https_are_the_same() {
+        assert_eq!(key("http://example.com/a"), key("https://example.com/a"));
+        assert_ne!(key("ftp://example.com/a"), key("https://example.com/a"));
+    }
+
+    #[test]
+    fn host_is_case_insensitive_without_www() {
+        assert_eq!(key("https://WWW.Example.COM/a"), "example.com/a");
+        // Only the host is case insensitive
+        assert_ne!(key("https://example.com/A"), key("https://example.com/a"));
+    }
+
+    #[test]
+    fn default_ports_are_dropped() {
+        assert_eq!(key("https://example.com:443/a"), "example.com/a");
+        assert_eq!(key("http://example.com:443/a"), "example.com/a");
+        assert_eq!(key("https://example.com:80/a"), "example.com/a");
+        assert_eq!(key("https://example.com:8080/a"), "example.com:8080/a");
+    }
+
+    #[test]
+    fn trailing_slashes_and_fragments_are_dropped() {
+        assert_eq!(key("https://example.com/"), "example.com");
+        assert_eq!(key("https://example.com"), "example.com");
+        assert_eq!(key("https://example.com/a/b/#section"), "example.com/a/b");
+    }
+
+    #[test]
+    fn tracking_params_are_dropped() {
+        assert_eq!(
+            key("https://example.com/a?utm_source=x&UTM_Medium=y&fbclid=1&gclid=2&id=3"),
+            "example.com/a?id=3"
+        );
+        assert_eq!(key("https://example.com/a?utm_campaign=x"), "example.com/a");
+        // Parameters merely starting like a tracking parameter are kept
+        assert_eq!(key("https://example.com/?gclidx=1"), "example.com?gclidx=1");
+    }
+
+    #[test]
+    fn params_are_sorted() {
+        assert_eq!(
+            key("https://example.com/?b=2&a=1"),
+            key("https://example.com/?a=1&b=2")
+        );
+        assert_eq!(key("https://example.com/?a=1&&b=2&"), "example.com?a=1&b=2");
+    }
+
+    #[test]
+    fn percent_encoding_is_normalized() {
+        assert_eq!(
+            key("https://example.com/%7euser/%41"),
+            "example.com/~user/A"
+        );
+        assert_eq!(
+            key("https://example.com/a%2fb?q=%e2%82%ac"),
+            "example.com/a%2Fb?q=%E2%82%AC"
+        );
+        // Reserved characters stay encoded
+        assert_ne!(
+            key("https://example.com/?q=a%26b"),
+            key("https://example.com/?q=a&b")
+        );
+        // Stray percent signs are kept as they are
+        assert_eq!(key("https://example.com/50%off"), "example.com/50%off");
+    }
+
+    #[test]
+    fn image_keys_ignore_resize_params() {
+        let key = |url: &str| canonical_image_key(&Url::parse(url).unwrap());
+
+        assert_eq!(
+            key("https://cdn.example.com/a.jpg?w=300&h=200&fit=crop"),
+            key("http://cdn.example.com/a.jpg")
+        );
+        // Images served from one endpoint are told apart by their query
+        assert_ne!(
+            key("https://example.com/image.php?id=1&w=300"),
+            key("https://example.com/image.php?id=2&w=300")
+        );
+        assert_eq!(
+            key("https://example.com/image.php?id=1&width=300"),
+            "example.com/image.php?id=1"
+        );
+    }
+
+    #[test]
+    fn display_url_prefers_https_without_tracking() {
+        let url = |url: &str| Url::parse(url).unwrap();
+
+        assert!(is_better_display_url(
+            &url("https://example.com/a"),
+            &url("http://example.com/a")
+        ));
+        assert!(is_better_display_url(
+            &url("https://www.example.com/a"),
+            &url("https://example.com/a?utm_source=x")
+        ));
+        assert!(is_better_display_url(
+            &url("https://example.com/a"),
+            &url("https://www.example.com/a")
+        ));
+        assert!(!is_better_display_url(
+            &url("http://example.com/a"),
+            &url("https://www.example.com/a")
+        ));
+    }
+}
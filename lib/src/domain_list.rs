@@ -0,0 +1,151 @@
+use std::{collections::HashSet, fs, path::Path};
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use url::Url;
+
+/// A list of domains used to filter or pin results.
+///
+/// Lists contain one entry per line, empty lines and lines starting with `#` are ignored.
+/// The following entries are supported:
+/// - `example.com` matches only the exact host.
+/// - `*.example.com` matches `example.com` and all of its subdomains.
+/// - `/^ex(ample)?\.com$/` matches all hosts matching the regex between the slashes.
+#[derive(Debug, Default)]
+pub struct DomainList {
+    exact: HashSet<String>,
+    wildcards: HashSet<String>,
+    regexes: Vec<Regex>,
+}
+
+impl DomainList {
+    /// Reads and parses a domain list from a file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let list = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read domain list {}", path.display()))?;
+
+        DomainList::parse(&list)
+            .with_context(|| format!("Failed to parse domain list {}", path.display()))
+    }
+
+    pub fn parse(list: &str) -> Result<Self> {
+        let mut domain_list = DomainList::default();
+
+        for (line_no, line) in list.lines().enumerate() {
+            let entry = line.trim();
+            if entry.is_empty() || entry.starts_with('#') {
+                continue;
+            }
+
+            if let Some(pattern) = entry
+                .strip_prefix('/')
+                .and_then(|entry| entry.strip_suffix('/'))
+            {
+                let regex = Regex::new(pattern)
+                    .with_context(|| format!("Invalid regex on line {}", line_no + 1))?;
+                domain_list.regexes.push(regex);
+            } else if let Some(domain) = entry.strip_prefix("*.") {
+                domain_list.wildcards.insert(domain.to_lowercase());
+            } else {
+                domain_list.exact.insert(entry.to_lowercase());
+            }
+        }
+
+        Ok(domain_list)
+    }
+
+    pub fn len(&self) -> usize {
+        self.exact.len() + self.wildcards.len() + self.regexes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Checks whether the host of the url matches any entry of the list.
+    pub fn is_match(&self, url: &Url) -> bool {
+        // Hosts are already lowercased by `Url`
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+
+        if self.exact.contains(host) {
+            return true;
+        }
+
+        let mut domain = host;
+        loop {
+            if self.wildcards.contains(domain) {
+                return true;
+            }
+            match domain.split_once('.') {
+                Some((_, parent)) => domain = parent,
+                None => break,
+            }
+        }
+
+        self.regexes.iter().any(|regex| regex.is_match(host))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_match(list: &DomainList, url: &str) -> bool {
+        list.is_match(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn exact_entries_only_match_the_host() {
+        let list = DomainList::parse("example.com").unwrap();
+
+        assert!(is_match(&list, "https://example.com/page"));
+        assert!(is_match(&list, "http://EXAMPLE.com"));
+        assert!(!is_match(&list, "https://www.example.com"));
+        assert!(!is_match(&list, "https://notexample.com"));
+    }
+
+    #[test]
+    fn wildcards_match_the_domain_and_subdomains() {
+        let list = DomainList::parse("*.example.com").unwrap();
+
+        assert!(is_match(&list, "https://example.com"));
+        assert!(is_match(&list, "https://www.example.com"));
+        assert!(is_match(&list, "https://a.b.example.com"));
+        assert!(!is_match(&list, "https://notexample.com"));
+        assert!(!is_match(&list, "https://example.com.evil.org"));
+    }
+
+    #[test]
+    fn regexes_match_the_host() {
+        let list = DomainList::parse(r"/^ex(ample)?\.com$/").unwrap();
+
+        assert!(is_match(&list, "https://ex.com"));
+        assert!(is_match(&list, "https://example.com/ex.com"));
+        assert!(!is_match(&list, "https://www.example.com"));
+    }
+
+    #[test]
+    fn comments_and_empty_lines_are_ignored() {
+        let list = DomainList::parse("# comment\n\n  example.com  \n*.Example.org\n").unwrap();
+
+        assert_eq!(list.len(), 2);
+        assert!(is_match(&list, "https://example.com"));
+        assert!(is_match(&list, "https://www.example.org"));
+    }
+
+    #[test]
+    fn invalid_regexes_are_rejected() {
+        assert!(DomainList::parse("example.com\n/(/").is_err());
+    }
+
+    #[test]
+    fn urls_without_a_host_never_match() {
+        let list = DomainList::parse("/.*/").unwrap();
+
+        assert!(!is_match(&list, "data:text/plain,example.com"));
+        assert!(DomainList::default().is_empty());
+    }
+}
@@ -1,19 +1,50 @@
 use std::{collections::HashMap, sync::Arc};
 
+use chrono::{DateTime, Duration, Utc};
 use regex::Regex;
 
 use reqwest::header::HeaderMap;
 use scraper::{Html, Selector};
+use serde::Deserialize;
 
 use crate::{
-    errors::EngineErrorType, network::NetworkHandler, Relavancy, SafeSearchLevel, SearchResult,
+    errors::EngineErrorType, network::NetworkHandler, Category, ImageResult, Locale, NewsResult,
+    Relavancy, SafeSearchLevel, SearchResult, VideoResult,
 };
 
-use super::{parse_generic_results, Engine};
+use super::{detect_captcha, parse_generic_results, Capabilities, Engine, UrlBuilder};
+
+/// Bing redirects to a challenge page when it suspects automated queries.
+const CAPTCHA_MARKERS: &[&str] = &["/turing/captcha/", "id=\"b_captcha\""];
 
 const COOKIE_PARAMS: &str =
     "_EDGE_V=1;SRCHD=AF=NOFORM;_Rwho=u=d;bngps=s=0;_UR=QS=0&TQS=0;_UR=QS=0&TQS=0;";
 
+/// Metadata stored as json in the `m` attribute of every image result.
+#[derive(Debug, Deserialize)]
+struct ImageMetadata {
+    // Full size image
+    murl: String,
+    // Thumbnail
+    turl: String,
+    // Page containing the image
+    purl: String,
+    #[serde(default)]
+    t: String,
+}
+
+/// Metadata stored as json in the `vrhm` attribute of every video result.
+#[derive(Debug, Deserialize)]
+struct VideoMetadata {
+    // Page of the video
+    murl: String,
+    // Title
+    vt: String,
+    // Duration formatted as `h:mm:ss`
+    #[serde(default)]
+    du: String,
+}
+
 #[derive(Debug)]
 pub struct Bing {
     no_results_selector: Selector,
@@ -21,22 +52,136 @@ pub struct Bing {
     text_result_url_selector: Selector,
     text_result_title_selector: Selector,
     text_result_desc_selector: Selector,
+    image_results_selector: Selector,
+    image_result_metadata_selector: Selector,
+    image_result_info_selector: Selector,
+    news_results_selector: Selector,
+    news_result_desc_selector: Selector,
+    news_result_age_selector: Selector,
+    news_result_thumbnail_selector: Selector,
+    video_results_selector: Selector,
+    video_result_metadata_selector: Selector,
+    video_result_thumbnail_selector: Selector,
+    video_result_channel_selector: Selector,
+    video_result_info_selector: Selector,
     re_strong: Regex,
     re_span: Regex,
+    re_dimensions: Regex,
+    re_age: Regex,
 }
 
 impl Bing {
-    pub fn new() -> Arc<Box<dyn Engine>> {
-        Arc::new(Box::new(Self {
+    pub fn new() -> Self {
+        Self {
             no_results_selector: Selector::parse(".b_results").unwrap(),
             text_results_selector: Selector::parse(".b_algo").unwrap(),
             text_result_url_selector: Selector::parse(".tpcn a.tilk").unwrap(),
             text_result_title_selector: Selector::parse("h2 a").unwrap(),
             text_result_desc_selector: Selector::parse(".b_caption p").unwrap(),
+            image_results_selector: Selector::parse("ul.dgControl_list>li").unwrap(),
+            image_result_metadata_selector: Selector::parse("a.iusc").unwrap(),
+            image_result_info_selector: Selector::parse(".img_info span.nowrap").unwrap(),
+            news_results_selector: Selector::parse("div.news-card").unwrap(),
+            news_result_desc_selector: Selector::parse(".snippet").unwrap(),
+            news_result_age_selector: Selector::parse(".source span[aria-label]").unwrap(),
+            news_result_thumbnail_selector: Selector::parse(".image img").unwrap(),
+            video_results_selector: Selector::parse("div.dg_u div.mc_vtvc").unwrap(),
+            video_result_metadata_selector: Selector::parse(".vrhdata").unwrap(),
+            video_result_thumbnail_selector: Selector::parse(".mc_vtvc_th img").unwrap(),
+            video_result_channel_selector: Selector::parse(".mc_vtvc_meta_row_channel").unwrap(),
+            video_result_info_selector: Selector::parse(".mc_vtvc_meta_block span").unwrap(),
 
             re_span: Regex::new(r#"<span.*?>.*?(?:</span>&nbsp;·|</span>)"#).unwrap(),
             re_strong: Regex::new(r#"(<strong>|</strong>)"#).unwrap(),
-        }))
+            re_dimensions: Regex::new(r#"(\d+)\s*[x×]\s*(\d+)"#).unwrap(),
+            re_age: Regex::new(r#"(\d+)\s*(minute|hour|day|week|month|year)"#).unwrap(),
+        }
+    }
+
+    /// Maps the relavancy to the value of bing's `ex1` filter.
+    fn relavancy_filter(relavancy: Relavancy) -> Option<String> {
+        match relavancy {
+            Relavancy::AnyTime => None,
+            Relavancy::PastDay => Some("ez1".to_string()),
+            Relavancy::PastWeek => Some("ez2".to_string()),
+            Relavancy::PastMonth => Some("ez3".to_string()),
+            Relavancy::PastYear => {
+                // Bing doesn't have a preset for a year, so a custom range is used instead. The
+                // range is given in days since the unix epoch.
+                let today =
+                    (Utc::now().date_naive() - DateTime::UNIX_EPOCH.date_naive()).num_days();
+                Some(format!("ez5_{}_{today}", today - 365))
+            }
+        }
+    }
+
+    /// Maps the relavancy to the maximum age (in minutes) used by bing's image and video filters.
+    fn image_relavancy_filter(relavancy: Relavancy) -> Option<u32> {
+        match relavancy {
+            Relavancy::AnyTime => None,
+            Relavancy::PastDay => Some(60 * 24),
+            Relavancy::PastWeek => Some(60 * 24 * 7),
+            Relavancy::PastMonth => Some(60 * 24 * 30),
+            Relavancy::PastYear => Some(60 * 24 * 365),
+        }
+    }
+
+    /// Maps the relavancy to the value of bing news' `interval` filter.
+    ///
+    /// There's no filter for a year, those results are filtered after aggregation.
+    fn news_relavancy_filter(relavancy: Relavancy) -> Option<u8> {
+        match relavancy {
+            Relavancy::AnyTime | Relavancy::PastYear => None,
+            Relavancy::PastDay => Some(7),
+            Relavancy::PastWeek => Some(8),
+            Relavancy::PastMonth => Some(9),
+        }
+    }
+
+    /// Bing news only shows the age of an article (`5 hours ago`), this converts it to a date.
+    fn parse_age(&self, age: &str) -> Option<DateTime<Utc>> {
+        let captures = self.re_age.captures(age)?;
+        let amount: i64 = captures[1].parse().ok()?;
+
+        let age = match &captures[2] {
+            "minute" => Duration::minutes(amount),
+            "hour" => Duration::hours(amount),
+            "day" => Duration::days(amount),
+            "week" => Duration::weeks(amount),
+            "month" => Duration::days(30 * amount),
+            _ => Duration::days(365 * amount),
+        };
+        Some(Utc::now() - age)
+    }
+
+    /// Converts a duration formatted as `h:mm:ss` or `m:ss` to seconds.
+    fn parse_duration(duration: &str) -> Option<u64> {
+        duration.trim().split(':').try_fold(0, |total, part| {
+            Some(total * 60 + part.parse::<u64>().ok()?)
+        })
+    }
+
+    /// Maps the locale to bing's `setlang` and `mkt` parameters.
+    fn locale_params(url: &mut UrlBuilder, locale: &Locale) {
+        url.param("setlang", &locale.language);
+        if let Some(region) = &locale.region {
+            url.param("mkt", format!("{}-{region}", locale.language));
+        }
+    }
+
+    /// Maps the safe search level to the value of bing's `adlt` parameter.
+    fn safe_search_param(safe_level: SafeSearchLevel) -> &'static str {
+        match safe_level {
+            SafeSearchLevel::Off => "off",
+            SafeSearchLevel::Low => "moderate",
+            SafeSearchLevel::Medium | SafeSearchLevel::High => "strict",
+        }
+    }
+}
+
+impl Default for Bing {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -45,20 +190,45 @@ impl Engine for Bing {
     fn get_name(&self) -> String {
         "Bing".to_string()
     }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            categories: vec![
+                Category::Text,
+                Category::Images,
+                Category::News,
+                Category::Videos,
+            ],
+            time_filter: true,
+            safe_search: true,
+            languages: true,
+            ..Default::default()
+        }
+    }
     async fn search_text(
         &self,
         qclient: Arc<NetworkHandler>,
         page_idx: u16,
         query: String,
-        _relavancy: Option<Relavancy>,
-        _safe_level: Option<SafeSearchLevel>,
+        relavancy: Option<Relavancy>,
+        safe_level: Option<SafeSearchLevel>,
+        locale: Option<Locale>,
     ) -> Result<Vec<SearchResult>, EngineErrorType> {
-        let cont_result = 10 * page_idx + 1;
+        let mut url = UrlBuilder::new("https://www.bing.com/search");
+        url.param("q", &query);
+        if page_idx != 0 {
+            url.param("first", 10 * u32::from(page_idx) + 1);
+        }
 
-        let url = match page_idx {
-            0 => format!("https://www.bing.com/search?q={query}"),
-            _ => format!("https://www.bing.com/search?q={query}&first={cont_result}"),
-        };
+        if let Some(filter) = relavancy.and_then(Bing::relavancy_filter) {
+            url.param("filters", format!("ex1:\"{filter}\""));
+        }
+        if let Some(safe_level) = safe_level {
+            url.param("adlt", Bing::safe_search_param(safe_level));
+        }
+        if let Some(locale) = locale {
+            Bing::locale_params(&mut url, &locale);
+        }
 
         let headers = HeaderMap::try_from(&HashMap::from([
             ("REFERER".to_string(), "https://google.com/".to_string()),
@@ -70,7 +240,8 @@ impl Engine for Bing {
         ]))
         .unwrap();
 
-        let page = qclient.get_data(&url, headers, false).await?;
+        let page = qclient.get_data(&url.build(), headers, false).await?;
+        detect_captcha("Bing", &page, CAPTCHA_MARKERS)?;
 
         let page = Html::parse_document(&page);
 
@@ -107,4 +278,235 @@ impl Engine for Bing {
         tracing::trace!("Bing returned {} results.", results.len());
         Ok(results)
     }
+    async fn search_images(
+        &self,
+        qclient: Arc<NetworkHandler>,
+        page_idx: u16,
+        query: String,
+        relavancy: Option<Relavancy>,
+        safe_level: Option<SafeSearchLevel>,
+        locale: Option<Locale>,
+    ) -> Result<Vec<ImageResult>, EngineErrorType> {
+        let mut url = UrlBuilder::new("https://www.bing.com/images/async");
+        url.param("q", &query)
+            .param("async", 1)
+            .param("count", 35)
+            .param("first", 35 * u32::from(page_idx) + 1);
+
+        if let Some(max_age) = relavancy.and_then(Bing::image_relavancy_filter) {
+            url.param("qft", format!("filterui:age-lt{max_age}"));
+        }
+        if let Some(safe_level) = safe_level {
+            url.param("adlt", Bing::safe_search_param(safe_level));
+        }
+        if let Some(locale) = locale {
+            Bing::locale_params(&mut url, &locale);
+        }
+
+        let headers = HeaderMap::try_from(&HashMap::from([
+            ("REFERER".to_string(), "https://www.bing.com/".to_string()),
+            ("COOKIE".to_string(), COOKIE_PARAMS.to_string()),
+        ]))
+        .unwrap();
+
+        let page = qclient.get_data(&url.build(), headers, false).await?;
+        detect_captcha("Bing", &page, CAPTCHA_MARKERS)?;
+
+        let page = Html::parse_document(&page);
+
+        let results = parse_generic_results(&page, &self.image_results_selector, |result| {
+            let metadata = result
+                .select(&self.image_result_metadata_selector)
+                .next()?
+                .value()
+                .attr("m")?;
+            let metadata: ImageMetadata = serde_json::from_str(metadata).ok()?;
+
+            let image = ImageResult::new(
+                &metadata.murl,
+                &metadata.turl,
+                &metadata.purl,
+                &metadata.t,
+                "Bing",
+            )
+            .ok()?;
+
+            // The info is formatted as `1920 x 1080 · jpeg`
+            let dimensions = result
+                .select(&self.image_result_info_selector)
+                .next()
+                .map(|info| info.text().collect::<String>())
+                .and_then(|info| {
+                    let captures = self.re_dimensions.captures(&info)?;
+                    Some((captures[1].parse().ok()?, captures[2].parse().ok()?))
+                });
+
+            match dimensions {
+                Some((width, height)) => Some(image.with_dimensions(width, height)),
+                None => Some(image),
+            }
+        })
+        .map_err(|_| EngineErrorType::ParseFailed)?;
+
+        tracing::trace!("Bing returned {} images.", results.len());
+        Ok(results)
+    }
+    async fn search_news(
+        &self,
+        qclient: Arc<NetworkHandler>,
+        page_idx: u16,
+        query: String,
+        relavancy: Option<Relavancy>,
+        safe_level: Option<SafeSearchLevel>,
+        locale: Option<Locale>,
+    ) -> Result<Vec<NewsResult>, EngineErrorType> {
+        let mut url = UrlBuilder::new("https://www.bing.com/news/infinitescrollajax");
+        url.param("q", &query)
+            .param("InfiniteScroll", 1)
+            .param("first", 10 * u32::from(page_idx) + 1);
+
+        if let Some(interval) = relavancy.and_then(Bing::news_relavancy_filter) {
+            url.param("qft", format!("interval=\"{interval}\""));
+        }
+        if let Some(safe_level) = safe_level {
+            url.param("adlt", Bing::safe_search_param(safe_level));
+        }
+        if let Some(locale) = locale {
+            Bing::locale_params(&mut url, &locale);
+        }
+
+        let headers = HeaderMap::try_from(&HashMap::from([
+            ("REFERER".to_string(), "https://www.bing.com/".to_string()),
+            ("COOKIE".to_string(), COOKIE_PARAMS.to_string()),
+        ]))
+        .unwrap();
+
+        let page = qclient.get_data(&url.build(), headers, false).await?;
+        detect_captcha("Bing", &page, CAPTCHA_MARKERS)?;
+
+        let page = Html::parse_document(&page);
+
+        let results = parse_generic_results(&page, &self.news_results_selector, |result| {
+            let url = result.value().attr("url")?;
+            let title = result.value().attr("data-title")?;
+            let desc = result
+                .select(&self.news_result_desc_selector)
+                .next()
+                .map(|desc| desc.text().collect::<String>())
+                .unwrap_or_default();
+
+            let mut news = NewsResult::new(url, title.trim(), desc.trim(), "Bing").ok()?;
+
+            if let Some(publisher) = result.value().attr("data-author") {
+                news = news.with_publisher(publisher);
+            }
+            if let Some(published_at) = result
+                .select(&self.news_result_age_selector)
+                .next()
+                .and_then(|age| age.value().attr("aria-label"))
+                .and_then(|age| self.parse_age(age))
+            {
+                news = news.with_published_at(published_at);
+            }
+            if let Some(thumbnail) = result
+                .select(&self.news_result_thumbnail_selector)
+                .next()
+                .and_then(|img| img.value().attr("data-src-hq").or(img.value().attr("src")))
+            {
+                // Thumbnails are served from bing with a relative url
+                news = if thumbnail.starts_with('/') {
+                    news.with_thumbnail(&format!("https://www.bing.com{thumbnail}"))
+                } else {
+                    news.with_thumbnail(thumbnail)
+                };
+            }
+
+            Some(news)
+        })
+        .map_err(|_| EngineErrorType::ParseFailed)?;
+
+        tracing::trace!("Bing returned {} news.", results.len());
+        Ok(results)
+    }
+    async fn search_videos(
+        &self,
+        qclient: Arc<NetworkHandler>,
+        page_idx: u16,
+        query: String,
+        relavancy: Option<Relavancy>,
+        safe_level: Option<SafeSearchLevel>,
+        locale: Option<Locale>,
+    ) -> Result<Vec<VideoResult>, EngineErrorType> {
+        let mut url = UrlBuilder::new("https://www.bing.com/videos/asyncv2");
+        url.param("q", &query)
+            .param("async", "content")
+            .param("count", 35)
+            .param("first", 35 * u32::from(page_idx) + 1);
+
+        if let Some(max_age) = relavancy.and_then(Bing::image_relavancy_filter) {
+            url.param("qft", format!("filterui:videoage-lt{max_age}"));
+        }
+        if let Some(safe_level) = safe_level {
+            url.param("adlt", Bing::safe_search_param(safe_level));
+        }
+        if let Some(locale) = locale {
+            Bing::locale_params(&mut url, &locale);
+        }
+
+        let headers = HeaderMap::try_from(&HashMap::from([
+            ("REFERER".to_string(), "https://www.bing.com/".to_string()),
+            ("COOKIE".to_string(), COOKIE_PARAMS.to_string()),
+        ]))
+        .unwrap();
+
+        let page = qclient.get_data(&url.build(), headers, false).await?;
+        detect_captcha("Bing", &page, CAPTCHA_MARKERS)?;
+
+        let page = Html::parse_document(&page);
+
+        let results = parse_generic_results(&page, &self.video_results_selector, |result| {
+            let metadata = result
+                .select(&self.video_result_metadata_selector)
+                .next()?
+                .value()
+                .attr("vrhm")?;
+            let metadata: VideoMetadata = serde_json::from_str(metadata).ok()?;
+
+            // The info is formatted as `1.2M views · 3 months ago`
+            let info = result
+                .select(&self.video_result_info_selector)
+                .map(|span| span.text().collect::<String>())
+                .collect::<Vec<String>>()
+                .join(" · ");
+
+            let mut video = VideoResult::new(&metadata.murl, &metadata.vt, &info, "Bing").ok()?;
+
+            if let Some(duration) = Bing::parse_duration(&metadata.du) {
+                video = video.with_duration(duration);
+            }
+            if let Some(channel) = result
+                .select(&self.video_result_channel_selector)
+                .next()
+                .map(|channel| channel.text().collect::<String>())
+            {
+                video = video.with_channel(channel.trim());
+            }
+            if let Some(published_at) = self.parse_age(&info) {
+                video = video.with_published_at(published_at);
+            }
+            if let Some(thumbnail) = result
+                .select(&self.video_result_thumbnail_selector)
+                .next()
+                .and_then(|img| img.value().attr("data-src-hq").or(img.value().attr("src")))
+            {
+                video = video.with_thumbnail(thumbnail);
+            }
+
+            Some(video)
+        })
+        .map_err(|_| EngineErrorType::ParseFailed)?;
+
+        tracing::trace!("Bing returned {} videos.", results.len());
+        Ok(results)
+    }
 }
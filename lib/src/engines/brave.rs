@@ -0,0 +1,240 @@
+use std::{collections::HashMap, sync::Arc};
+
+use regex::Regex;
+use reqwest::header::HeaderMap;
+use scraper::{Html, Selector};
+use serde::Deserialize;
+
+use crate::{
+    errors::EngineErrorType, network::NetworkHandler, Locale, Relavancy, SafeSearchLevel,
+    SearchResult,
+};
+
+use super::{detect_captcha, parse_generic_results, Capabilities, Engine, UrlBuilder};
+
+/// Brave asks to solve a captcha when it suspects automated queries.
+const CAPTCHA_MARKERS: &[&str] = &["/search/captcha", "id=\"captcha\""];
+
+#[derive(Debug, Deserialize)]
+struct ApiResponse {
+    web: Option<ApiWebResults>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiWebResults {
+    results: Vec<ApiResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiResult {
+    title: String,
+    url: String,
+    #[serde(default)]
+    description: String,
+}
+
+/// Brave search can either be scraped or be queried through the official API.
+///
+/// The API is used when an API key is provided as it is far more reliable than scraping.
+#[derive(Debug)]
+pub struct Brave {
+    api_key: Option<String>,
+    text_results_selector: Selector,
+    text_result_url_selector: Selector,
+    text_result_title_selector: Selector,
+    text_result_desc_selector: Selector,
+    re_strong: Regex,
+}
+
+impl Brave {
+    pub fn new(api_key: Option<String>) -> Self {
+        Self {
+            api_key,
+            text_results_selector: Selector::parse(r#"#results .snippet[data-type="web"]"#)
+                .unwrap(),
+            text_result_url_selector: Selector::parse("a").unwrap(),
+            text_result_title_selector: Selector::parse(".title").unwrap(),
+            text_result_desc_selector: Selector::parse(".snippet-description").unwrap(),
+
+            re_strong: Regex::new(r#"(<strong>|</strong>)"#).unwrap(),
+        }
+    }
+
+    /// Maps the relavancy to the value of brave's `tf` and `freshness` parameters.
+    fn relavancy_filter(relavancy: Relavancy) -> Option<&'static str> {
+        match relavancy {
+            Relavancy::AnyTime => None,
+            Relavancy::PastDay => Some("pd"),
+            Relavancy::PastWeek => Some("pw"),
+            Relavancy::PastMonth => Some("pm"),
+            Relavancy::PastYear => Some("py"),
+        }
+    }
+
+    /// Maps the safe search level to the value of brave's `safesearch` parameter.
+    fn safe_search_param(safe_level: SafeSearchLevel) -> &'static str {
+        match safe_level {
+            SafeSearchLevel::Off => "off",
+            SafeSearchLevel::Low => "moderate",
+            SafeSearchLevel::Medium | SafeSearchLevel::High => "strict",
+        }
+    }
+
+    async fn search_api(
+        &self,
+        qclient: Arc<NetworkHandler>,
+        page_idx: u16,
+        query: String,
+        relavancy: Option<Relavancy>,
+        safe_level: Option<SafeSearchLevel>,
+        locale: Option<Locale>,
+    ) -> Result<Vec<SearchResult>, EngineErrorType> {
+        let Some(api_key) = &self.api_key else {
+            return Err(EngineErrorType::Unknown(
+                "Brave API key is not set".to_string(),
+            ));
+        };
+
+        let mut url = UrlBuilder::new("https://api.search.brave.com/res/v1/web/search");
+        url.param("q", &query).param("offset", page_idx);
+
+        if let Some(filter) = relavancy.and_then(Brave::relavancy_filter) {
+            url.param("freshness", filter);
+        }
+        if let Some(safe_level) = safe_level {
+            url.param("safesearch", Brave::safe_search_param(safe_level));
+        }
+        if let Some(locale) = locale {
+            url.param("search_lang", &locale.language);
+            if let Some(region) = locale.region {
+                url.param("country", region);
+            }
+        }
+
+        let headers = HeaderMap::try_from(&HashMap::from([(
+            "X-Subscription-Token".to_string(),
+            api_key.to_string(),
+        )]))
+        .map_err(|_| EngineErrorType::Unknown("Invalid Brave API key".to_string()))?;
+
+        let response = qclient.get_data(&url.build(), headers, true).await?;
+        let response: ApiResponse =
+            serde_json::from_str(&response).map_err(|_| EngineErrorType::ParseFailed)?;
+
+        let results: Vec<SearchResult> = response
+            .web
+            .ok_or(EngineErrorType::NoResults)?
+            .results
+            .into_iter()
+            .filter_map(|result| {
+                SearchResult::new(
+                    &result.url,
+                    &result.title,
+                    &self.re_strong.replace_all(&result.description, ""),
+                    "Brave",
+                )
+                .ok()
+            })
+            .collect();
+
+        tracing::trace!("Brave API returned {} results.", results.len());
+        Ok(results)
+    }
+
+    async fn search_html(
+        &self,
+        qclient: Arc<NetworkHandler>,
+        page_idx: u16,
+        query: String,
+        relavancy: Option<Relavancy>,
+        safe_level: Option<SafeSearchLevel>,
+        locale: Option<Locale>,
+    ) -> Result<Vec<SearchResult>, EngineErrorType> {
+        let mut url = UrlBuilder::new("https://search.brave.com/search");
+        url.param("q", &query)
+            .param("offset", page_idx)
+            .param("source", "web");
+
+        if let Some(filter) = relavancy.and_then(Brave::relavancy_filter) {
+            url.param("tf", filter);
+        }
+
+        let safe_search = Brave::safe_search_param(safe_level.unwrap_or(SafeSearchLevel::Low));
+        let mut cookies = format!("safesearch={safe_search};");
+        if let Some(locale) = locale {
+            cookies.push_str(&format!("search_lang={};", locale.language));
+            if let Some(region) = locale.region {
+                cookies.push_str(&format!("country={};", region.to_lowercase()));
+            }
+        }
+        let headers = HeaderMap::try_from(&HashMap::from([
+            (
+                "REFERER".to_string(),
+                "https://search.brave.com/".to_string(),
+            ),
+            ("COOKIE".to_string(), cookies),
+        ]))
+        .unwrap();
+
+        let page = qclient.get_data(&url.build(), headers, false).await?;
+        detect_captcha("Brave", &page, CAPTCHA_MARKERS)?;
+
+        let page = Html::parse_document(&page);
+
+        let results = parse_generic_results(&page, &self.text_results_selector, |result| {
+            let title = result.select(&self.text_result_title_selector).next();
+            let url = result.select(&self.text_result_url_selector).next();
+            let desc = result.select(&self.text_result_desc_selector).next();
+
+            if let (Some(title), Some(url), Some(desc)) = (title, url, desc) {
+                SearchResult::new(
+                    url.value().attr("href")?,
+                    title.text().collect::<String>().trim(),
+                    desc.text().collect::<String>().trim(),
+                    "Brave",
+                )
+                .ok()
+            } else {
+                None
+            }
+        })
+        .map_err(|_| EngineErrorType::ParseFailed)?;
+
+        tracing::trace!("Brave returned {} results.", results.len());
+        Ok(results)
+    }
+}
+
+#[async_trait::async_trait]
+impl Engine for Brave {
+    fn get_name(&self) -> String {
+        "Brave".to_string()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            time_filter: true,
+            safe_search: true,
+            languages: true,
+            ..Default::default()
+        }
+    }
+
+    async fn search_text(
+        &self,
+        qclient: Arc<NetworkHandler>,
+        page_idx: u16,
+        query: String,
+        relavancy: Option<Relavancy>,
+        safe_level: Option<SafeSearchLevel>,
+        locale: Option<Locale>,
+    ) -> Result<Vec<SearchResult>, EngineErrorType> {
+        if self.api_key.is_some() {
+            self.search_api(qclient, page_idx, query, relavancy, safe_level, locale)
+                .await
+        } else {
+            self.search_html(qclient, page_idx, query, relavancy, safe_level, locale)
+                .await
+        }
+    }
+}
@@ -1,13 +1,63 @@
 use std::{collections::HashMap, sync::Arc};
 
+use chrono::DateTime;
+use regex::Regex;
 use reqwest::header::HeaderMap;
 use scraper::{Html, Selector};
+use serde::Deserialize;
 
 use crate::{
-    errors::EngineErrorType, network::NetworkHandler, Relavancy, SafeSearchLevel, SearchResult,
+    errors::EngineErrorType, network::NetworkHandler, Category, ImageResult, Locale, NewsResult,
+    Relavancy, SafeSearchLevel, SearchResult,
 };
 
-use super::{parse_generic_results, Engine};
+use super::{detect_captcha, parse_generic_results, Capabilities, Engine, UrlBuilder};
+
+/// Region codes of duckduckgo which aren't made of the ISO codes of the region and language, by
+/// language and region.
+const REGION_CODES: &[(&str, &str, &str)] = &[
+    ("en", "GB", "uk-en"),
+    ("ja", "JP", "jp-jp"),
+    ("ko", "KR", "kr-kr"),
+    ("zh", "HK", "hk-tzh"),
+    ("zh", "TW", "tw-tzh"),
+];
+
+/// Duckduckgo shows an anomaly page with a captcha when it suspects automated queries.
+const CAPTCHA_MARKERS: &[&str] = &["anomaly-modal", "/anomaly.js"];
+
+#[derive(Debug, Deserialize)]
+struct ImageResponse {
+    results: Vec<ImageHit>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ImageHit {
+    #[serde(default)]
+    title: String,
+    image: String,
+    thumbnail: String,
+    url: String,
+    width: Option<u32>,
+    height: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NewsResponse {
+    results: Vec<NewsHit>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NewsHit {
+    title: String,
+    url: String,
+    #[serde(default)]
+    excerpt: String,
+    source: Option<String>,
+    // Unix timestamp
+    date: Option<i64>,
+    image: Option<String>,
+}
 
 #[derive(Debug)]
 pub struct DuckDuckGo {
@@ -16,17 +66,95 @@ pub struct DuckDuckGo {
     text_result_url_selector: Selector,
     text_result_title_selector: Selector,
     text_result_desc_selector: Selector,
+    re_vqd: Regex,
 }
 
 impl DuckDuckGo {
-    pub fn new() -> Arc<Box<dyn Engine>> {
-        Arc::new(Box::new(Self {
+    pub fn new() -> Self {
+        Self {
             no_results_selector: Selector::parse(".no-results").unwrap(),
             text_results_selector: Selector::parse(".results>.result").unwrap(),
             text_result_url_selector: Selector::parse(".result__url").unwrap(),
             text_result_title_selector: Selector::parse(".result__title>.result__a").unwrap(),
             text_result_desc_selector: Selector::parse(".result__snippet").unwrap(),
-        }))
+
+            re_vqd: Regex::new(r#"vqd=["']?([\d-]+)"#).unwrap(),
+        }
+    }
+
+    /// Fetches the `vqd` token which is required for querying the json endpoints.
+    async fn fetch_vqd(
+        &self,
+        qclient: &NetworkHandler,
+        query: &str,
+    ) -> Result<String, EngineErrorType> {
+        let url = UrlBuilder::new("https://duckduckgo.com/")
+            .param("q", query)
+            .build();
+        let page = qclient.get_data(&url, HeaderMap::new(), false).await?;
+        detect_captcha("DuckDuckGo", &page, CAPTCHA_MARKERS)?;
+
+        self.re_vqd
+            .captures(&page)
+            .map(|captures| captures[1].to_string())
+            .ok_or(EngineErrorType::ParseFailed)
+    }
+
+    /// Maps the relavancy to the time filter of duckduckgo's image search.
+    fn image_relavancy_filter(relavancy: Relavancy) -> Option<&'static str> {
+        match relavancy {
+            Relavancy::AnyTime => None,
+            Relavancy::PastDay => Some("Day"),
+            Relavancy::PastWeek => Some("Week"),
+            Relavancy::PastMonth => Some("Month"),
+            Relavancy::PastYear => Some("Year"),
+        }
+    }
+
+    /// Maps the relavancy to the value of duckduckgo's `df` parameter.
+    fn relavancy_filter(relavancy: Relavancy) -> Option<&'static str> {
+        match relavancy {
+            Relavancy::AnyTime => None,
+            Relavancy::PastDay => Some("d"),
+            Relavancy::PastWeek => Some("w"),
+            Relavancy::PastMonth => Some("m"),
+            Relavancy::PastYear => Some("y"),
+        }
+    }
+
+    /// Maps the locale to duckduckgo's region code, like `us-en`.
+    ///
+    /// Duckduckgo only has codes for a language within a region, so `wt-wt` (no region) is used when
+    /// the locale has no region.
+    fn region_param(locale: Option<&Locale>) -> String {
+        match locale {
+            Some(Locale {
+                language,
+                region: Some(region),
+            }) => REGION_CODES
+                .iter()
+                .find(|(code_language, code_region, _)| {
+                    code_language == language && code_region == region
+                })
+                .map(|(_, _, code)| code.to_string())
+                .unwrap_or_else(|| format!("{}-{language}", region.to_lowercase())),
+            _ => "wt-wt".to_string(),
+        }
+    }
+
+    /// Maps the safe search level to the value of duckduckgo's `kp` parameter.
+    fn safe_search_param(safe_level: SafeSearchLevel) -> &'static str {
+        match safe_level {
+            SafeSearchLevel::Off => "-2",
+            SafeSearchLevel::Low => "-1",
+            SafeSearchLevel::Medium | SafeSearchLevel::High => "1",
+        }
+    }
+}
+
+impl Default for DuckDuckGo {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -36,32 +164,55 @@ impl Engine for DuckDuckGo {
         "DuckDuckGo".to_string()
     }
 
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            categories: vec![Category::Text, Category::Images, Category::News],
+            time_filter: true,
+            safe_search: true,
+            languages: true,
+            ..Default::default()
+        }
+    }
+
     async fn search_text(
         &self,
         qclient: Arc<NetworkHandler>,
-        mut page_idx: u16,
+        page_idx: u16,
         query: String,
-        _relavancy: Option<Relavancy>,
-        _safe_level: Option<SafeSearchLevel>,
+        relavancy: Option<Relavancy>,
+        safe_level: Option<SafeSearchLevel>,
+        locale: Option<Locale>,
     ) -> Result<Vec<SearchResult>, EngineErrorType> {
-        let url: String = match page_idx {
+        let mut url = match page_idx {
             0 => {
-                format!("https://html.duckduckgo.com/html/?q={query}&s=&dc=&v=1&o=json&api=/d.js")
+                let mut url = UrlBuilder::new("https://html.duckduckgo.com/html/");
+                url.param("q", &query).param("s", "").param("dc", "");
+                url
             }
             _ => {
-                if page_idx == 2 {
-                    page_idx = 20;
+                let offset = if page_idx == 2 {
+                    20
                 } else {
                     // The pattern is: 20, 70, 120
-                    page_idx = ((page_idx - 1) * 50) + 20;
-                }
-                format!(
-                    "https://duckduckgo.com/html/?q={query}&s={}&dc={}&v=1&o=json&api=/d.js",
-                    page_idx,
-                    page_idx + 1
-                )
+                    ((u32::from(page_idx) - 1) * 50) + 20
+                };
+                let mut url = UrlBuilder::new("https://duckduckgo.com/html/");
+                url.param("q", &query)
+                    .param("s", offset)
+                    .param("dc", offset + 1);
+                url
             }
         };
+        url.param("v", 1).param("o", "json").param("api", "/d.js");
+
+        let region = DuckDuckGo::region_param(locale.as_ref());
+        url.param("kl", &region);
+        if let Some(filter) = relavancy.and_then(DuckDuckGo::relavancy_filter) {
+            url.param("df", filter);
+        }
+        if let Some(safe_level) = safe_level {
+            url.param("kp", DuckDuckGo::safe_search_param(safe_level));
+        }
 
         let headers = HeaderMap::try_from(&HashMap::from([
             ("REFERER".to_string(), "https://google.com/".to_string()),
@@ -69,11 +220,12 @@ impl Engine for DuckDuckGo {
                 "CONTENT_TYPE".to_string(),
                 "application/x-www-form-urlencoded".to_string(),
             ),
-            ("COOKIE".to_string(), "kl=wt-wt".to_string()),
+            ("COOKIE".to_string(), format!("kl={region}")),
         ]))
         .unwrap();
 
-        let page = qclient.get_data(&url, headers, false).await?;
+        let page = qclient.get_data(&url.build(), headers, false).await?;
+        detect_captcha("DuckDuckGo", &page, CAPTCHA_MARKERS)?;
 
         let page = Html::parse_document(&page);
 
@@ -107,4 +259,140 @@ impl Engine for DuckDuckGo {
         tracing::trace!("DuckDuckGo returned {} results.", results.len());
         Ok(results)
     }
+    async fn search_images(
+        &self,
+        qclient: Arc<NetworkHandler>,
+        page_idx: u16,
+        query: String,
+        relavancy: Option<Relavancy>,
+        safe_level: Option<SafeSearchLevel>,
+        locale: Option<Locale>,
+    ) -> Result<Vec<ImageResult>, EngineErrorType> {
+        let vqd = self.fetch_vqd(&qclient, &query).await?;
+
+        let time_filter = relavancy
+            .and_then(DuckDuckGo::image_relavancy_filter)
+            .map(|filter| format!("time:{filter}"))
+            .unwrap_or_default();
+        // The image search only supports turning safe search on or off
+        let safe_search = match safe_level {
+            Some(level) if level >= SafeSearchLevel::Medium => "1",
+            _ => "-1",
+        };
+
+        let region = DuckDuckGo::region_param(locale.as_ref());
+        let url = UrlBuilder::new("https://duckduckgo.com/i.js")
+            .param("q", &query)
+            .param("o", "json")
+            .param("l", region)
+            .param("vqd", vqd)
+            .param("f", format!("{time_filter},,,,,"))
+            .param("p", safe_search)
+            .param("s", 100 * u32::from(page_idx))
+            .build();
+
+        let headers = HeaderMap::try_from(&HashMap::from([(
+            "REFERER".to_string(),
+            "https://duckduckgo.com/".to_string(),
+        )]))
+        .unwrap();
+
+        let response = qclient.get_data(&url, headers, true).await?;
+        let response: ImageResponse =
+            serde_json::from_str(&response).map_err(|_| EngineErrorType::ParseFailed)?;
+
+        if response.results.is_empty() {
+            return Err(EngineErrorType::NoResults);
+        }
+
+        let results: Vec<ImageResult> = response
+            .results
+            .into_iter()
+            .filter_map(|hit| {
+                let image = ImageResult::new(
+                    &hit.image,
+                    &hit.thumbnail,
+                    &hit.url,
+                    &hit.title,
+                    "DuckDuckGo",
+                )
+                .ok()?;
+
+                match (hit.width, hit.height) {
+                    (Some(width), Some(height)) => Some(image.with_dimensions(width, height)),
+                    _ => Some(image),
+                }
+            })
+            .collect();
+
+        tracing::trace!("DuckDuckGo returned {} images.", results.len());
+        Ok(results)
+    }
+    async fn search_news(
+        &self,
+        qclient: Arc<NetworkHandler>,
+        page_idx: u16,
+        query: String,
+        relavancy: Option<Relavancy>,
+        safe_level: Option<SafeSearchLevel>,
+        locale: Option<Locale>,
+    ) -> Result<Vec<NewsResult>, EngineErrorType> {
+        let vqd = self.fetch_vqd(&qclient, &query).await?;
+
+        let region = DuckDuckGo::region_param(locale.as_ref());
+        let mut url = UrlBuilder::new("https://duckduckgo.com/news.js");
+        url.param("q", &query)
+            .param("o", "json")
+            .param("l", region)
+            .param("noamp", 1)
+            .param("vqd", vqd)
+            .param("s", 30 * u32::from(page_idx));
+
+        if let Some(filter) = relavancy.and_then(DuckDuckGo::relavancy_filter) {
+            url.param("df", filter);
+        }
+        if let Some(safe_level) = safe_level {
+            url.param("p", DuckDuckGo::safe_search_param(safe_level));
+        }
+
+        let headers = HeaderMap::try_from(&HashMap::from([(
+            "REFERER".to_string(),
+            "https://duckduckgo.com/".to_string(),
+        )]))
+        .unwrap();
+
+        let response = qclient.get_data(&url.build(), headers, true).await?;
+        let response: NewsResponse =
+            serde_json::from_str(&response).map_err(|_| EngineErrorType::ParseFailed)?;
+
+        if response.results.is_empty() {
+            return Err(EngineErrorType::NoResults);
+        }
+
+        let results: Vec<NewsResult> = response
+            .results
+            .into_iter()
+            .filter_map(|hit| {
+                let mut news =
+                    NewsResult::new(&hit.url, &hit.title, &hit.excerpt, "DuckDuckGo").ok()?;
+
+                if let Some(publisher) = hit.source {
+                    news = news.with_publisher(&publisher);
+                }
+                if let Some(published_at) =
+                    hit.date.and_then(|date| DateTime::from_timestamp(date, 0))
+                {
+                    news = news.with_published_at(published_at);
+                }
+                if let Some(image) = hit.image {
+                    news = news.with_thumbnail(&image);
+                }
+
+                Some(news)
+            })
+            .collect();
+
+        tracing::trace!("DuckDuckGo returned {} news.", results.len());
+        Ok(results)
+    }
 }
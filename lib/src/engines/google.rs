@@ -0,0 +1,167 @@
+use std::{collections::HashMap, sync::Arc};
+
+use reqwest::header::HeaderMap;
+use scraper::{Html, Selector};
+use url::Url;
+
+use crate::{
+    errors::EngineErrorType, network::NetworkHandler, Locale, Relavancy, SafeSearchLevel,
+    SearchResult,
+};
+
+use super::{detect_captcha, parse_generic_results, Capabilities, Engine, UrlBuilder};
+
+/// Skips the consent page shown to clients from the EU.
+const COOKIE_PARAMS: &str = "CONSENT=YES+;";
+/// Google serves an interstitial with a captcha when it suspects automated queries.
+const CAPTCHA_MARKERS: &[&str] = &["detected unusual traffic", "id=\"captcha-form\""];
+
+#[derive(Debug)]
+pub struct Google {
+    no_results_selector: Selector,
+    text_results_selector: Selector,
+    text_result_url_selector: Selector,
+    text_result_title_selector: Selector,
+    text_result_desc_selector: Selector,
+}
+
+impl Google {
+    pub fn new() -> Self {
+        Self {
+            no_results_selector: Selector::parse(".card-section").unwrap(),
+            text_results_selector: Selector::parse("#main>div>.Gx5Zad").unwrap(),
+            text_result_url_selector: Selector::parse(r#"a[href^="/url?q="]"#).unwrap(),
+            text_result_title_selector: Selector::parse("h3").unwrap(),
+            text_result_desc_selector: Selector::parse(".BNeawe.s3v9rd.AP7Wnd").unwrap(),
+        }
+    }
+
+    /// Maps the relavancy to the value of google's `tbs=qdr:` parameter.
+    fn relavancy_filter(relavancy: Relavancy) -> Option<&'static str> {
+        match relavancy {
+            Relavancy::AnyTime => None,
+            Relavancy::PastDay => Some("d"),
+            Relavancy::PastWeek => Some("w"),
+            Relavancy::PastMonth => Some("m"),
+            Relavancy::PastYear => Some("y"),
+        }
+    }
+
+    /// Maps the safe search level to the value of google's `safe` parameter.
+    fn safe_search_param(safe_level: SafeSearchLevel) -> &'static str {
+        match safe_level {
+            SafeSearchLevel::Off => "off",
+            SafeSearchLevel::Low => "medium",
+            SafeSearchLevel::Medium | SafeSearchLevel::High => "high",
+        }
+    }
+
+    /// Google wraps the result links in a redirect (`/url?q=<url>&sa=...`), this extracts the
+    /// actual url.
+    fn unwrap_redirect(href: &str) -> Option<String> {
+        let redirect = Url::parse("https://www.google.com").ok()?.join(href).ok()?;
+        redirect
+            .query_pairs()
+            .find(|(key, _)| key == "q")
+            .map(|(_, url)| url.into_owned())
+    }
+}
+
+impl Default for Google {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait::async_trait]
+impl Engine for Google {
+    fn get_name(&self) -> String {
+        "Google".to_string()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            time_filter: true,
+            safe_search: true,
+            languages: true,
+            ..Default::default()
+        }
+    }
+
+    async fn search_text(
+        &self,
+        qclient: Arc<NetworkHandler>,
+        page_idx: u16,
+        query: String,
+        relavancy: Option<Relavancy>,
+        safe_level: Option<SafeSearchLevel>,
+        locale: Option<Locale>,
+    ) -> Result<Vec<SearchResult>, EngineErrorType> {
+        // `gbv=1` requests the basic html version which doesn't require javascript
+        let mut url = UrlBuilder::new("https://www.google.com/search");
+        url.param("q", &query)
+            .param("gbv", 1)
+            .param("start", 10 * u32::from(page_idx));
+
+        if let Some(filter) = relavancy.and_then(Google::relavancy_filter) {
+            url.param("tbs", format!("qdr:{filter}"));
+        }
+        if let Some(safe_level) = safe_level {
+            url.param("safe", Google::safe_search_param(safe_level));
+        }
+        if let Some(locale) = locale {
+            // `hl` sets the interface language and `lr` restricts the results to the language
+            url.param("hl", &locale.language)
+                .param("lr", format!("lang_{}", locale.language));
+            if let Some(region) = locale.region {
+                url.param("gl", region);
+            }
+        }
+
+        let headers = HeaderMap::try_from(&HashMap::from([
+            ("REFERER".to_string(), "https://www.google.com/".to_string()),
+            ("COOKIE".to_string(), COOKIE_PARAMS.to_string()),
+        ]))
+        .unwrap();
+
+        let page = qclient.get_data(&url.build(), headers, false).await?;
+        detect_captcha("Google", &page, CAPTCHA_MARKERS)?;
+
+        let page = Html::parse_document(&page);
+
+        if let Some(no_result_msg) = page.select(&self.no_results_selector).next() {
+            if no_result_msg
+                .text()
+                .any(|text| text.contains("did not match any documents"))
+            {
+                return Err(EngineErrorType::NoResults);
+            }
+        }
+
+        let results = parse_generic_results(&page, &self.text_results_selector, |result| {
+            let title = result.select(&self.text_result_title_selector).next();
+            let url = result
+                .select(&self.text_result_url_selector)
+                .next()
+                .and_then(|url| url.value().attr("href"))
+                .and_then(Google::unwrap_redirect);
+            let desc = result.select(&self.text_result_desc_selector).last();
+
+            if let (Some(title), Some(url), Some(desc)) = (title, url, desc) {
+                SearchResult::new(
+                    &url,
+                    title.text().collect::<String>().trim(),
+                    desc.text().collect::<String>().trim(),
+                    "Google",
+                )
+                .ok()
+            } else {
+                None
+            }
+        })
+        .map_err(|_| EngineErrorType::ParseFailed)?;
+
+        tracing::trace!("Google returned {} results.", results.len());
+        Ok(results)
+    }
+}
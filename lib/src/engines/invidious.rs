@@ -0,0 +1,156 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use chrono::DateTime;
+use reqwest::header::HeaderMap;
+use serde::Deserialize;
+use url::Url;
+
+use crate::{
+    errors::EngineErrorType, network::NetworkHandler, Category, Locale, Relavancy, SafeSearchLevel,
+    VideoResult,
+};
+
+use super::{Capabilities, Engine, UrlBuilder};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiVideo {
+    title: String,
+    video_id: String,
+    #[serde(default)]
+    author: Option<String>,
+    #[serde(default)]
+    description: String,
+    length_seconds: Option<u64>,
+    // Unix timestamp
+    published: Option<i64>,
+    #[serde(default)]
+    video_thumbnails: Vec<ApiThumbnail>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiThumbnail {
+    quality: String,
+    url: String,
+}
+
+/// Invidious is a front-end for youtube, it is queried through the API of a configurable instance.
+///
+/// Results link to youtube so that they can be merged with the ones from other engines.
+#[derive(Debug)]
+pub struct Invidious {
+    base_url: Url,
+}
+
+impl Invidious {
+    pub fn new(base_url: Option<&str>) -> Result<Self> {
+        let base_url = base_url.context("Invidious requires the base_url of an instance")?;
+
+        Ok(Self {
+            base_url: Url::parse(base_url)
+                .with_context(|| format!("Invalid Invidious base_url {base_url}"))?,
+        })
+    }
+
+    /// Maps the relavancy to the value of invidious' `date` parameter.
+    fn relavancy_filter(relavancy: Relavancy) -> Option<&'static str> {
+        match relavancy {
+            Relavancy::AnyTime => None,
+            Relavancy::PastDay => Some("today"),
+            Relavancy::PastWeek => Some("week"),
+            Relavancy::PastMonth => Some("month"),
+            Relavancy::PastYear => Some("year"),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Engine for Invidious {
+    fn get_name(&self) -> String {
+        "Invidious".to_string()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            categories: vec![Category::Videos],
+            time_filter: true,
+            languages: true,
+            ..Default::default()
+        }
+    }
+
+    async fn search_videos(
+        &self,
+        qclient: Arc<NetworkHandler>,
+        page_idx: u16,
+        query: String,
+        relavancy: Option<Relavancy>,
+        _safe_level: Option<SafeSearchLevel>,
+        locale: Option<Locale>,
+    ) -> Result<Vec<VideoResult>, EngineErrorType> {
+        let mut url = UrlBuilder::from(self.base_url.clone());
+        url.path(["api", "v1", "search"])
+            .param("q", &query)
+            .param("type", "video")
+            .param("page", u32::from(page_idx) + 1);
+
+        if let Some(filter) = relavancy.and_then(Invidious::relavancy_filter) {
+            url.param("date", filter);
+        }
+        // Invidious can only restrict the results to a region
+        if let Some(region) = locale.and_then(|locale| locale.region) {
+            url.param("region", region);
+        }
+
+        let response = qclient
+            .get_data(&url.build(), HeaderMap::new(), true)
+            .await?;
+        let response: Vec<ApiVideo> =
+            serde_json::from_str(&response).map_err(|_| EngineErrorType::ParseFailed)?;
+
+        if response.is_empty() {
+            return Err(EngineErrorType::NoResults);
+        }
+
+        let results: Vec<VideoResult> = response
+            .into_iter()
+            .filter_map(|hit| {
+                let mut video = VideoResult::new(
+                    &format!("https://www.youtube.com/watch?v={}", hit.video_id),
+                    &hit.title,
+                    &hit.description,
+                    "Invidious",
+                )
+                .ok()?;
+
+                if let Some(duration) = hit.length_seconds {
+                    video = video.with_duration(duration);
+                }
+                if let Some(author) = hit.author {
+                    video = video.with_channel(&author);
+                }
+                if let Some(published_at) = hit
+                    .published
+                    .and_then(|date| DateTime::from_timestamp(date, 0))
+                {
+                    video = video.with_published_at(published_at);
+                }
+                // Some instances proxy the thumbnails and return relative urls
+                if let Some(thumbnail) = hit
+                    .video_thumbnails
+                    .iter()
+                    .find(|thumbnail| thumbnail.quality == "medium")
+                    .and_then(|thumbnail| self.base_url.join(&thumbnail.url).ok())
+                {
+                    video = video.with_thumbnail(thumbnail.as_str());
+                }
+
+                Some(video)
+            })
+            .collect();
+
+        tracing::trace!("Invidious returned {} videos.", results.len());
+        Ok(results)
+    }
+}
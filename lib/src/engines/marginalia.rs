@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+use reqwest::header::HeaderMap;
+use serde::Deserialize;
+
+use crate::{
+    errors::EngineErrorType, network::NetworkHandler, Locale, Relavancy, SafeSearchLevel,
+    SearchResult,
+};
+
+use super::{Capabilities, Engine, UrlBuilder};
+
+/// Key which is shared by everyone using the API without registering.
+const PUBLIC_API_KEY: &str = "public";
+/// Maximum number of results returned by the API for a query.
+const MAX_RESULTS: u16 = 100;
+
+#[derive(Debug, Deserialize)]
+struct ApiResponse {
+    results: Vec<ApiResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiResult {
+    url: String,
+    title: String,
+    #[serde(default)]
+    description: String,
+}
+
+/// Marginalia indexes the small, non-commercial web and is queried through its JSON API.
+#[derive(Debug)]
+pub struct Marginalia {
+    api_key: String,
+}
+
+impl Marginalia {
+    pub fn new(api_key: Option<String>) -> Self {
+        Self {
+            api_key: api_key.unwrap_or_else(|| PUBLIC_API_KEY.to_string()),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Engine for Marginalia {
+    fn get_name(&self) -> String {
+        "Marginalia".to_string()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            max_page: Some(MAX_RESULTS / 10 - 1),
+            ..Default::default()
+        }
+    }
+
+    async fn search_text(
+        &self,
+        qclient: Arc<NetworkHandler>,
+        page_idx: u16,
+        query: String,
+        _relavancy: Option<Relavancy>,
+        _safe_level: Option<SafeSearchLevel>,
+        _locale: Option<Locale>,
+    ) -> Result<Vec<SearchResult>, EngineErrorType> {
+        // The API doesn't support pagination, so all the results up to the requested page are
+        // fetched and the previous pages are skipped.
+        let skip = 10 * page_idx;
+        let count = skip + 10;
+        if count > MAX_RESULTS {
+            return Err(EngineErrorType::NoResults);
+        }
+
+        let url = UrlBuilder::new("https://api.marginalia.nu/")
+            .path([self.api_key.as_str(), "search", &query])
+            .param("count", count)
+            .build();
+
+        let response = qclient.get_data(&url, HeaderMap::new(), true).await?;
+        let response: ApiResponse =
+            serde_json::from_str(&response).map_err(|_| EngineErrorType::ParseFailed)?;
+
+        if response.results.is_empty() {
+            return Err(EngineErrorType::NoResults);
+        }
+
+        let results: Vec<SearchResult> = response
+            .results
+            .into_iter()
+            .skip(skip.into())
+            .filter_map(|result| {
+                SearchResult::new(
+                    &result.url,
+                    &result.title,
+                    &result.description,
+                    "Marginalia",
+                )
+                .ok()
+            })
+            .collect();
+
+        tracing::trace!("Marginalia returned {} results.", results.len());
+        Ok(results)
+    }
+}
@@ -1,23 +1,65 @@
 pub mod bing;
+pub mod brave;
 pub mod duckduckgo;
+pub mod google;
+pub mod invidious;
+pub mod marginalia;
+pub mod mojeek;
+pub mod selector;
+pub mod wikipedia;
 
-use std::fmt::Debug;
+use std::fmt::{Debug, Display};
 use std::sync::Arc;
 
 use scraper::{ElementRef, Html, Selector};
 use tracing::instrument;
+use url::{form_urlencoded, Url};
 
 use crate::{
-    errors::EngineErrorType, network::NetworkHandler, Relavancy, SafeSearchLevel, SearchResult,
+    errors::EngineErrorType, network::NetworkHandler, Category, ImageResult, Infobox, Locale,
+    NewsResult, Relavancy, SafeSearchLevel, SearchResult, VideoResult,
 };
 
+/// Describes the searches an engine can serve, engines are only queried for searches they support.
+#[derive(Debug, Clone)]
+pub struct Capabilities {
+    /// Categories with an implemented search_* method.
+    pub categories: Vec<Category>,
+    /// Whether results can be restricted to a time relavancy.
+    pub time_filter: bool,
+    /// Whether the engine filters explicit results itself.
+    pub safe_search: bool,
+    /// Whether results can be restricted to a language, the locale is only passed to engines which
+    /// can.
+    pub languages: bool,
+    /// Index of the last page the engine can return, if there's a limit.
+    pub max_page: Option<u16>,
+}
+
+impl Default for Capabilities {
+    /// Text search without any filters.
+    fn default() -> Self {
+        Self {
+            categories: vec![Category::Text],
+            time_filter: false,
+            safe_search: false,
+            languages: false,
+            max_page: None,
+        }
+    }
+}
+
 /// The base trait that all upstream search engine parsers should implement.
 ///
-/// All engines must implement at least one of the search_* methods
+/// All engines must implement at least one of the search_* methods and list the categories of
+/// the implemented methods in their [`Capabilities`]. The methods which aren't implemented fail
+/// with `EngineErrorType::Unsupported`.
 #[async_trait::async_trait]
 pub trait Engine: Send + Sync + Debug {
     fn get_name(&self) -> String;
 
+    fn capabilities(&self) -> Capabilities;
+
     #[instrument(level = "TRACE", skip(_query))]
     async fn search_text(
         &self,
@@ -26,19 +68,134 @@ pub trait Engine: Send + Sync + Debug {
         _query: String,
         _relavancy: Option<Relavancy>,
         _safe_level: Option<SafeSearchLevel>,
+        _locale: Option<Locale>,
     ) -> Result<Vec<SearchResult>, EngineErrorType> {
-        unimplemented!()
+        Err(EngineErrorType::Unsupported)
+    }
+
+    #[instrument(level = "TRACE", skip(_query))]
+    async fn search_images(
+        &self,
+        _qclient: Arc<NetworkHandler>,
+        _page_idx: u16,
+        _query: String,
+        _relavancy: Option<Relavancy>,
+        _safe_level: Option<SafeSearchLevel>,
+        _locale: Option<Locale>,
+    ) -> Result<Vec<ImageResult>, EngineErrorType> {
+        Err(EngineErrorType::Unsupported)
+    }
+
+    #[instrument(level = "TRACE", skip(_query))]
+    async fn search_news(
+        &self,
+        _qclient: Arc<NetworkHandler>,
+        _page_idx: u16,
+        _query: String,
+        _relavancy: Option<Relavancy>,
+        _safe_level: Option<SafeSearchLevel>,
+        _locale: Option<Locale>,
+    ) -> Result<Vec<NewsResult>, EngineErrorType> {
+        Err(EngineErrorType::Unsupported)
+    }
+
+    #[instrument(level = "TRACE", skip(_query))]
+    async fn search_videos(
+        &self,
+        _qclient: Arc<NetworkHandler>,
+        _page_idx: u16,
+        _query: String,
+        _relavancy: Option<Relavancy>,
+        _safe_level: Option<SafeSearchLevel>,
+        _locale: Option<Locale>,
+    ) -> Result<Vec<VideoResult>, EngineErrorType> {
+        Err(EngineErrorType::Unsupported)
+    }
+
+    /// Looks up a summary of the topic of the query.
+    ///
+    /// Engines which can't provide infoboxes don't need to implement this.
+    async fn search_infobox(
+        &self,
+        _qclient: Arc<NetworkHandler>,
+        _query: String,
+        _locale: Option<Locale>,
+    ) -> Result<Option<Infobox>, EngineErrorType> {
+        Ok(None)
+    }
+}
+
+/// Builds the url of a request to an engine.
+///
+/// Path segments and query parameters are percent-encoded, so queries containing `&`, `#`, `+`
+/// or non-ascii characters reach the engine as they were typed.
+#[derive(Debug, Clone)]
+pub struct UrlBuilder {
+    url: Url,
+}
+
+impl UrlBuilder {
+    /// Panics if `base` isn't an absolute url, it's expected to be a constant of the engine.
+    pub fn new(base: &str) -> Self {
+        Self::from(Url::parse(base).expect("Invalid engine url"))
+    }
+
+    /// Appends segments to the path, `/` within a segment is encoded.
+    pub fn path<I>(&mut self, segments: I) -> &mut Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        if let Ok(mut path) = self.url.path_segments_mut() {
+            path.pop_if_empty().extend(segments);
+        }
+        self
+    }
+
+    /// Appends a query parameter.
+    pub fn param(&mut self, key: &str, value: impl Display) -> &mut Self {
+        self.url
+            .query_pairs_mut()
+            .append_pair(key, &value.to_string());
+        self
+    }
+
+    pub fn build(&self) -> String {
+        self.url.to_string()
+    }
+}
+
+impl From<Url> for UrlBuilder {
+    fn from(url: Url) -> Self {
+        Self { url }
+    }
+}
+
+/// Encodes a value for use within the query string of a url, for urls which can't be built with
+/// a [`UrlBuilder`].
+pub fn encode_component(value: &str) -> String {
+    form_urlencoded::byte_serialize(value.as_bytes()).collect()
+}
+
+/// Fails with `EngineErrorType::Ratelimited` when the page contains any of the `markers` which
+/// identify the captcha or block page of an engine.
+pub fn detect_captcha(
+    engine: &str,
+    page: &str,
+    markers: &[impl AsRef<str>],
+) -> Result<(), EngineErrorType> {
+    if markers.iter().any(|marker| page.contains(marker.as_ref())) {
+        tracing::debug!("{engine} returned a captcha");
+        return Err(EngineErrorType::Ratelimited(None));
     }
+    Ok(())
 }
 
 /// A helper function to select the main the "results" part of a page.
-pub fn parse_generic_results(
+pub fn parse_generic_results<T>(
     page: &Html,
     results_selector: &Selector,
-    builder: impl Fn(ElementRef<'_>) -> Option<SearchResult>,
-) -> anyhow::Result<Vec<SearchResult>> {
-    Ok(page
-        .select(results_selector)
-        .filter_map(|result| builder(result))
-        .collect())
+    builder: impl Fn(ElementRef<'_>) -> Option<T>,
+) -> anyhow::Result<Vec<T>> {
+    Ok(page.select(results_selector).filter_map(builder).collect())
 }
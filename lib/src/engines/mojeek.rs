@@ -0,0 +1,138 @@
+use std::{collections::HashMap, sync::Arc};
+
+use chrono::{Duration, Utc};
+use reqwest::header::HeaderMap;
+use scraper::{Html, Selector};
+
+use crate::{
+    errors::EngineErrorType, network::NetworkHandler, Locale, Relavancy, SafeSearchLevel,
+    SearchResult,
+};
+
+use super::{detect_captcha, parse_generic_results, Capabilities, Engine, UrlBuilder};
+
+/// Mojeek shows a block page to clients sending automated queries.
+const CAPTCHA_MARKERS: &[&str] = &["appears to be sending automated queries"];
+
+/// Mojeek maintains its own index, independent of Bing and Google.
+#[derive(Debug)]
+pub struct Mojeek {
+    text_results_selector: Selector,
+    text_result_url_selector: Selector,
+    text_result_title_selector: Selector,
+    text_result_desc_selector: Selector,
+}
+
+impl Mojeek {
+    pub fn new() -> Self {
+        Self {
+            text_results_selector: Selector::parse("ul.results-standard>li").unwrap(),
+            text_result_url_selector: Selector::parse("a.ob").unwrap(),
+            text_result_title_selector: Selector::parse("h2>a.title").unwrap(),
+            text_result_desc_selector: Selector::parse("p.s").unwrap(),
+        }
+    }
+
+    /// Maps the relavancy to the value of mojeek's `since` parameter, which is a date formatted as
+    /// `YYYYMMDD`.
+    fn relavancy_filter(relavancy: Relavancy) -> Option<String> {
+        let days = match relavancy {
+            Relavancy::AnyTime => return None,
+            Relavancy::PastDay => 1,
+            Relavancy::PastWeek => 7,
+            Relavancy::PastMonth => 30,
+            Relavancy::PastYear => 365,
+        };
+
+        Some(
+            (Utc::now() - Duration::days(days))
+                .format("%Y%m%d")
+                .to_string(),
+        )
+    }
+}
+
+impl Default for Mojeek {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait::async_trait]
+impl Engine for Mojeek {
+    fn get_name(&self) -> String {
+        "Mojeek".to_string()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            time_filter: true,
+            safe_search: true,
+            languages: true,
+            ..Default::default()
+        }
+    }
+
+    async fn search_text(
+        &self,
+        qclient: Arc<NetworkHandler>,
+        page_idx: u16,
+        query: String,
+        relavancy: Option<Relavancy>,
+        safe_level: Option<SafeSearchLevel>,
+        locale: Option<Locale>,
+    ) -> Result<Vec<SearchResult>, EngineErrorType> {
+        let mut url = UrlBuilder::new("https://www.mojeek.com/search");
+        url.param("q", &query);
+        if page_idx != 0 {
+            url.param("s", 10 * u32::from(page_idx) + 1);
+        }
+
+        if let Some(since) = relavancy.and_then(Mojeek::relavancy_filter) {
+            url.param("since", since);
+        }
+        if let Some(safe_level) = safe_level {
+            url.param("safe", u8::from(safe_level >= SafeSearchLevel::Medium));
+        }
+        if let Some(locale) = locale {
+            // Mojeek only biases the results towards the language and region
+            url.param("lb", &locale.language);
+            if let Some(region) = locale.region {
+                url.param("arc", region.to_lowercase());
+            }
+        }
+
+        let headers = HeaderMap::try_from(&HashMap::from([(
+            "REFERER".to_string(),
+            "https://www.mojeek.com/".to_string(),
+        )]))
+        .unwrap();
+
+        let page = qclient.get_data(&url.build(), headers, false).await?;
+        detect_captcha("Mojeek", &page, CAPTCHA_MARKERS)?;
+
+        let page = Html::parse_document(&page);
+
+        let results = parse_generic_results(&page, &self.text_results_selector, |result| {
+            let title = result.select(&self.text_result_title_selector).next();
+            let url = result.select(&self.text_result_url_selector).next();
+            let desc = result.select(&self.text_result_desc_selector).next();
+
+            if let (Some(title), Some(url), Some(desc)) = (title, url, desc) {
+                SearchResult::new(
+                    url.value().attr("href")?,
+                    title.text().collect::<String>().trim(),
+                    desc.text().collect::<String>().trim(),
+                    "Mojeek",
+                )
+                .ok()
+            } else {
+                None
+            }
+        })
+        .map_err(|_| EngineErrorType::ParseFailed)?;
+
+        tracing::trace!("Mojeek returned {} results.", results.len());
+        Ok(results)
+    }
+}
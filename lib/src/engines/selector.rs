@@ -0,0 +1,202 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::{anyhow, Context, Result};
+use regex::Regex;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use scraper::{Html, Selector};
+use serde::Deserialize;
+
+use crate::{
+    errors::EngineErrorType, network::NetworkHandler, Locale, Relavancy, SafeSearchLevel,
+    SearchResult,
+};
+
+use super::{detect_captcha, encode_component, parse_generic_results, Capabilities, Engine};
+
+/// Describes an engine which is scraped with css selectors, read from the config file.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SelectorDefinition {
+    /// Url of the results page, `{query}` and `{page}` are replaced with the url-encoded query and
+    /// the value of the page parameter.
+    pub url: String,
+    /// Value of `{page}` for the first page.
+    #[serde(default)]
+    pub page_start: u32,
+    /// Amount `{page}` is increased by for every following page.
+    #[serde(default = "default_page_step")]
+    pub page_step: u32,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    #[serde(default)]
+    pub cookies: HashMap<String, String>,
+    /// Selector matching every result on the page.
+    pub results: String,
+    /// Selectors for the fields, matched within a result.
+    pub title: String,
+    pub url_selector: String,
+    /// Attribute of `url_selector` containing the link, its text is used when set to `text`.
+    #[serde(default = "default_url_attribute")]
+    pub url_attribute: String,
+    pub description: String,
+    /// Selector for the element shown when there are no results.
+    pub no_results: Option<String>,
+    /// Regexes which are removed from the titles and descriptions.
+    #[serde(default)]
+    pub cleanup: Vec<String>,
+    /// Text which is only found on the captcha or block page of the engine.
+    #[serde(default)]
+    pub captcha: Vec<String>,
+}
+
+fn default_page_step() -> u32 {
+    1
+}
+
+fn default_url_attribute() -> String {
+    "href".to_string()
+}
+
+/// Engine built from a [`SelectorDefinition`].
+#[derive(Debug)]
+pub struct SelectorEngine {
+    name: String,
+    url: String,
+    page_start: u32,
+    page_step: u32,
+    headers: HeaderMap,
+    results_selector: Selector,
+    result_title_selector: Selector,
+    result_url_selector: Selector,
+    url_attribute: String,
+    result_desc_selector: Selector,
+    no_results_selector: Option<Selector>,
+    cleanup: Vec<Regex>,
+    captcha: Vec<String>,
+}
+
+impl SelectorEngine {
+    pub fn new(name: &str, definition: &SelectorDefinition) -> Result<Self> {
+        let mut headers = HeaderMap::new();
+        for (key, value) in &definition.headers {
+            headers.insert(
+                HeaderName::try_from(key)
+                    .with_context(|| format!("{name}: invalid header {key}"))?,
+                HeaderValue::try_from(value)
+                    .with_context(|| format!("{name}: invalid value for header {key}"))?,
+            );
+        }
+        if !definition.cookies.is_empty() {
+            let cookies = definition
+                .cookies
+                .iter()
+                .map(|(key, value)| format!("{key}={value}"))
+                .collect::<Vec<String>>()
+                .join("; ");
+            headers.insert(
+                reqwest::header::COOKIE,
+                HeaderValue::try_from(cookies)
+                    .with_context(|| format!("{name}: invalid cookies"))?,
+            );
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            url: definition.url.clone(),
+            page_start: definition.page_start,
+            page_step: definition.page_step,
+            headers,
+            results_selector: parse_selector(name, &definition.results)?,
+            result_title_selector: parse_selector(name, &definition.title)?,
+            result_url_selector: parse_selector(name, &definition.url_selector)?,
+            url_attribute: definition.url_attribute.clone(),
+            result_desc_selector: parse_selector(name, &definition.description)?,
+            no_results_selector: definition
+                .no_results
+                .as_ref()
+                .map(|selector| parse_selector(name, selector))
+                .transpose()?,
+            cleanup: definition
+                .cleanup
+                .iter()
+                .map(|re| Regex::new(re).with_context(|| format!("{name}: invalid regex {re}")))
+                .collect::<Result<Vec<Regex>>>()?,
+            captcha: definition.captcha.clone(),
+        })
+    }
+
+    fn clean(&self, text: &str) -> String {
+        let text = self.cleanup.iter().fold(text.to_string(), |text, re| {
+            re.replace_all(&text, "").to_string()
+        });
+        text.trim().to_string()
+    }
+}
+
+fn parse_selector(name: &str, selector: &str) -> Result<Selector> {
+    Selector::parse(selector).map_err(|err| anyhow!("{name}: invalid selector {selector}: {err}"))
+}
+
+#[async_trait::async_trait]
+impl Engine for SelectorEngine {
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::default()
+    }
+
+    async fn search_text(
+        &self,
+        qclient: Arc<NetworkHandler>,
+        page_idx: u16,
+        query: String,
+        _relavancy: Option<Relavancy>,
+        _safe_level: Option<SafeSearchLevel>,
+        _locale: Option<Locale>,
+    ) -> Result<Vec<SearchResult>, EngineErrorType> {
+        let page = self.page_start + self.page_step * u32::from(page_idx);
+        let url = self
+            .url
+            .replace("{query}", &encode_component(&query))
+            .replace("{page}", &page.to_string());
+
+        let page = qclient.get_data(&url, self.headers.clone(), false).await?;
+        detect_captcha(&self.name, &page, &self.captcha)?;
+
+        let page = Html::parse_document(&page);
+
+        if let Some(no_results_selector) = &self.no_results_selector {
+            if page.select(no_results_selector).next().is_some() {
+                tracing::trace!("{} returned no results", self.name);
+                return Err(EngineErrorType::NoResults);
+            }
+        }
+
+        let results = parse_generic_results(&page, &self.results_selector, |result| {
+            let title = result.select(&self.result_title_selector).next()?;
+            let url = result.select(&self.result_url_selector).next()?;
+            let url = match self.url_attribute.as_str() {
+                "text" => url.text().collect::<String>(),
+                attribute => url.value().attr(attribute)?.to_string(),
+            };
+            let desc = result
+                .select(&self.result_desc_selector)
+                .next()
+                .map(|desc| desc.text().collect::<String>())
+                .unwrap_or_default();
+
+            SearchResult::new(
+                url.trim(),
+                &self.clean(&title.text().collect::<String>()),
+                &self.clean(&desc),
+                &self.name,
+            )
+            .ok()
+        })
+        .map_err(|_| EngineErrorType::ParseFailed)?;
+
+        tracing::trace!("{} returned {} results.", self.name, results.len());
+        Ok(results)
+    }
+}
@@ -0,0 +1,225 @@
+use std::sync::Arc;
+
+use regex::Regex;
+use reqwest::header::HeaderMap;
+use serde::Deserialize;
+use url::Url;
+
+use crate::{
+    errors::EngineErrorType, network::NetworkHandler, Infobox, Locale, Relavancy, SafeSearchLevel,
+    SearchResult,
+};
+
+use super::{Capabilities, Engine, UrlBuilder};
+
+/// Languages with a wikipedia edition, other languages fall back to english.
+const LANGUAGES: &[&str] = &[
+    "af", "ar", "arz", "ast", "az", "azb", "be", "bg", "bn", "br", "bs", "ca", "ce", "ceb", "cs",
+    "cy", "da", "de", "el", "en", "eo", "es", "et", "eu", "fa", "fi", "fr", "ga", "gl", "he", "hi",
+    "hr", "hu", "hy", "id", "is", "it", "ja", "ka", "kk", "ko", "la", "lb", "lt", "lv", "mk", "ml",
+    "mn", "mr", "ms", "my", "nl", "nn", "no", "pa", "pl", "pt", "ro", "ru", "sh", "si", "sk", "sl",
+    "sq", "sr", "sv", "sw", "ta", "te", "tg", "th", "tl", "tr", "tt", "uk", "ur", "uz", "vi",
+    "war", "zh",
+];
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    query: SearchQuery,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchQuery {
+    search: Vec<SearchHit>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchHit {
+    title: String,
+    #[serde(default)]
+    snippet: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SummaryResponse {
+    #[serde(rename = "type")]
+    page_type: String,
+    title: String,
+    extract: String,
+    thumbnail: Option<SummaryThumbnail>,
+    content_urls: SummaryUrls,
+}
+
+#[derive(Debug, Deserialize)]
+struct SummaryThumbnail {
+    source: Url,
+}
+
+#[derive(Debug, Deserialize)]
+struct SummaryUrls {
+    desktop: SummaryPageUrl,
+}
+
+#[derive(Debug, Deserialize)]
+struct SummaryPageUrl {
+    page: Url,
+}
+
+/// Queries the MediaWiki search API of wikipedia.
+///
+/// When the top result's title matches the query, the page summary is used as an infobox.
+#[derive(Debug)]
+pub struct Wikipedia {
+    re_tags: Regex,
+}
+
+impl Wikipedia {
+    pub fn new() -> Self {
+        Self {
+            re_tags: Regex::new(r#"<[^>]*>"#).unwrap(),
+        }
+    }
+
+    /// Every language has its own wikipedia, english is used when no locale is given or the
+    /// language has no edition.
+    fn host(locale: Option<&Locale>) -> String {
+        let language = locale
+            .map(|locale| locale.language.as_str())
+            .filter(|language| LANGUAGES.contains(language))
+            .unwrap_or("en");
+        format!("https://{language}.wikipedia.org")
+    }
+
+    async fn search_titles(
+        &self,
+        qclient: &NetworkHandler,
+        host: &str,
+        query: &str,
+        offset: u32,
+        limit: u16,
+    ) -> Result<Vec<SearchHit>, EngineErrorType> {
+        let url = UrlBuilder::new(host)
+            .path(["w", "api.php"])
+            .param("action", "query")
+            .param("list", "search")
+            .param("format", "json")
+            .param("srsearch", query)
+            .param("sroffset", offset)
+            .param("srlimit", limit)
+            .build();
+
+        let response = qclient.get_data(&url, HeaderMap::new(), true).await?;
+        let response: SearchResponse =
+            serde_json::from_str(&response).map_err(|_| EngineErrorType::ParseFailed)?;
+
+        Ok(response.query.search)
+    }
+
+    fn page_url(host: &str, title: &str) -> String {
+        UrlBuilder::new(host)
+            .path(["wiki", &title.replace(' ', "_")])
+            .build()
+    }
+}
+
+impl Default for Wikipedia {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait::async_trait]
+impl Engine for Wikipedia {
+    fn get_name(&self) -> String {
+        "Wikipedia".to_string()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            languages: true,
+            ..Default::default()
+        }
+    }
+
+    async fn search_text(
+        &self,
+        qclient: Arc<NetworkHandler>,
+        page_idx: u16,
+        query: String,
+        _relavancy: Option<Relavancy>,
+        _safe_level: Option<SafeSearchLevel>,
+        locale: Option<Locale>,
+    ) -> Result<Vec<SearchResult>, EngineErrorType> {
+        let host = Wikipedia::host(locale.as_ref());
+        let hits = self
+            .search_titles(&qclient, &host, &query, 10 * u32::from(page_idx), 10)
+            .await?;
+
+        if hits.is_empty() {
+            return Err(EngineErrorType::NoResults);
+        }
+
+        let results: Vec<SearchResult> = hits
+            .into_iter()
+            .filter_map(|hit| {
+                SearchResult::new(
+                    &Wikipedia::page_url(&host, &hit.title),
+                    &hit.title,
+                    &self.re_tags.replace_all(&hit.snippet, ""),
+                    "Wikipedia",
+                )
+                .ok()
+            })
+            .collect();
+
+        tracing::trace!("Wikipedia returned {} results.", results.len());
+        Ok(results)
+    }
+
+    async fn search_infobox(
+        &self,
+        qclient: Arc<NetworkHandler>,
+        query: String,
+        locale: Option<Locale>,
+    ) -> Result<Option<Infobox>, EngineErrorType> {
+        let host = Wikipedia::host(locale.as_ref());
+        let Some(top_hit) = self
+            .search_titles(&qclient, &host, &query, 0, 1)
+            .await?
+            .into_iter()
+            .next()
+        else {
+            return Ok(None);
+        };
+
+        // Only show a summary when the query is clearly about the page
+        if !top_hit.title.eq_ignore_ascii_case(query.trim()) {
+            return Ok(None);
+        }
+
+        let url = UrlBuilder::new(&host)
+            .path([
+                "api",
+                "rest_v1",
+                "page",
+                "summary",
+                &top_hit.title.replace(' ', "_"),
+            ])
+            .build();
+        let response = qclient.get_data(&url, HeaderMap::new(), true).await?;
+        let summary: SummaryResponse =
+            serde_json::from_str(&response).map_err(|_| EngineErrorType::ParseFailed)?;
+
+        // Disambiguation pages don't have a meaningful summary
+        if summary.page_type == "disambiguation" {
+            return Ok(None);
+        }
+
+        Ok(Some(Infobox {
+            title: summary.title,
+            extract: summary.extract,
+            thumbnail: summary.thumbnail.map(|thumbnail| thumbnail.source),
+            url: summary.content_urls.desktop.page,
+            source: "Wikipedia".to_string(),
+        }))
+    }
+}
@@ -12,16 +12,58 @@ pub struct EngineError {
 pub enum EngineErrorType {
     #[error("Failed to parse")]
     ParseFailed,
+    /// Raised on captchas and rate limiting responses, holds the seconds the engine asked to wait
+    /// for when it did.
     #[error("Ratelimited by upstream engine")]
-    Ratelimited,
+    Ratelimited(Option<u64>),
     #[error("No results returned for query")]
     NoResults,
+    /// Raised when the engine doesn't respond within its configured timeout (in milliseconds).
+    #[error("Engine did not respond within {0}ms")]
+    Timeout(u128),
     #[error("Failed to spawn search task")]
     ExecFailed,
+    /// Raised when the engine is queried for a category it doesn't implement.
+    #[error("Search is not supported by the engine")]
+    Unsupported,
+    /// Raised when the engine is skipped after repeated failures, holds the seconds until it's
+    /// retried.
+    #[error("Engine is suspended after repeated failures, retrying in {0}s")]
+    Suspended(u64),
     #[error("Unknown error occured: {0}")]
     Unknown(String),
     #[error("Network error occured")]
-    Network(#[from] NetworkError),
+    Network(#[source] NetworkError),
+}
+
+impl From<NetworkError> for EngineErrorType {
+    fn from(value: NetworkError) -> Self {
+        match value {
+            NetworkError::Ratelimited(retry_after) => EngineErrorType::Ratelimited(retry_after),
+            error => EngineErrorType::Network(error),
+        }
+    }
+}
+
+/// Engine which wasn't queried as it can't serve the search.
+#[derive(Debug, Error, Serialize)]
+#[error("{engine} was skipped: {reason}")]
+pub struct SkippedEngine {
+    pub engine: String,
+    pub reason: SkipReason,
+}
+
+#[derive(Debug, Error, Serialize)]
+pub enum SkipReason {
+    #[error("Category is not supported")]
+    UnsupportedCategory,
+    #[error("Requested time relavancy is not supported")]
+    UnsupportedRelavancy,
+    /// Raised when safe search is requested but the engine can't filter explicit results.
+    #[error("Safe search is not supported")]
+    UnsupportedSafeSearch,
+    #[error("Page is past the last page the engine can return")]
+    PageOutOfRange,
 }
 
 #[derive(Debug, Error, Serialize)]
@@ -32,6 +74,19 @@ pub enum NetworkError {
     /// Raised when connection to upstream search engine timesout.
     #[error("Request to {0} has timed out.")]
     ConnectionTimeout(String),
+    /// Raised on `429 Too Many Requests` and `503 Service Unavailable`, holds the seconds from the
+    /// `Retry-After` header.
+    #[error("Ratelimited by upstream engine")]
+    Ratelimited(Option<u64>),
+    /// Raised on `403 Forbidden`, usually because the engine has blocked our address.
+    #[error("Access to {0} is forbidden")]
+    Blocked(String),
+    /// Raised on other 5xx statuses.
+    #[error("{0} failed with status {1}")]
+    Upstream(String, u16),
+    /// Raised on other unsuccessful statuses.
+    #[error("{0} returned unexpected status {1}")]
+    UnexpectedStatus(String, u16),
     /// Raised when there are problems while parsing or something else happens.
     #[error("Unknown error occured: {0}")]
     Unknown(String),
@@ -1,37 +1,61 @@
 use crate::{
-    engines::{bing::Bing, duckduckgo::DuckDuckGo, Engine},
-    errors::{EngineError, EngineErrorType},
+    engines::{Capabilities, Engine},
+    errors::{EngineError, EngineErrorType, SkipReason, SkippedEngine},
+    health::{EngineHealth, EngineStats},
     network::NetworkHandler,
-    Relavancy, SafeSearchLevel, SearchResult,
+    registry::EngineRegistry,
+    Category, ImageResult, Infobox, Locale, NewsResult, Relavancy, SafeSearchLevel, SearchResult,
+    SelectorDefinition, VideoResult,
 };
 use anyhow::Result;
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 use tokio::task::{Id, JoinSet};
 use tracing::instrument;
 
+/// Settings for an individual engine.
+#[derive(Debug, Clone)]
+pub struct EngineSettings {
+    /// Maximum time the engine is allowed to take for a search.
+    pub timeout: Duration,
+    /// Key for engines which can be queried through an official API.
+    pub api_key: Option<String>,
+    /// Instance to query for engines which are front-ends hosted by third parties.
+    pub base_url: Option<String>,
+    /// Definition of an engine which is scraped with selectors from the config.
+    pub selector: Option<SelectorDefinition>,
+}
+
 #[derive(Debug)]
 pub struct EngineHandler {
-    engines: Vec<Arc<Box<dyn Engine>>>,
+    // Engines along with the maximum time they are allowed to take for a search
+    engines: Vec<(Arc<Box<dyn Engine>>, Duration)>,
+    // Keyed by the name of the engine
+    health: Mutex<HashMap<String, EngineHealth>>,
     query_client: Arc<NetworkHandler>,
 }
 
 impl EngineHandler {
-    /// Create a new engine handler based on provided list of engines.
-    #[instrument(level = "TRACE", skip(network_handler))]
+    /// Create a new engine handler based on provided engines and their settings.
+    ///
+    /// The engines are built with the `registry`, unknown engine names are an error.
+    #[instrument(level = "TRACE", skip(registry, network_handler))]
     pub fn new(
-        activated_engines: &[String],
+        activated_engines: &HashMap<String, EngineSettings>,
+        registry: &EngineRegistry,
         network_handler: NetworkHandler,
     ) -> Result<EngineHandler> {
-        let mut engines: Vec<Arc<Box<dyn Engine>>> = vec![];
+        let mut engines: Vec<(Arc<Box<dyn Engine>>, Duration)> = vec![];
 
-        for engine in activated_engines {
-            // Add new engines here
-            if engine.eq_ignore_ascii_case("bing") {
-                engines.push(Bing::new())
-            }
-            if engine.eq_ignore_ascii_case("duckduckgo") {
-                engines.push(DuckDuckGo::new())
-            }
+        for (engine, settings) in activated_engines {
+            engines.push((
+                Arc::new(registry.build(engine, settings)?),
+                settings.timeout,
+            ));
         }
         if engines.is_empty() {
             tracing::warn!(
@@ -41,16 +65,32 @@ impl EngineHandler {
 
         tracing::info!("Initialized {} engines", engines.len());
 
+        let health = engines
+            .iter()
+            .map(|(engine, timeout)| (engine.get_name(), EngineHealth::new(*timeout)))
+            .collect();
+
         Ok(EngineHandler {
             engines,
+            health: Mutex::new(health),
             query_client: Arc::new(network_handler),
         })
     }
 
+    /// Statistics of the recent searches of every engine.
+    pub fn stats(&self) -> Vec<EngineStats> {
+        let health = self.health.lock().unwrap();
+        let mut stats: Vec<EngineStats> = health
+            .iter()
+            .map(|(engine, health)| health.stats(engine))
+            .collect();
+        stats.sort_by(|a, b| a.engine.cmp(&b.engine));
+        stats
+    }
+
     /// Concurrently search the query with all the selected engines.
     ///
-    /// An async task is spun up for every engine and is executed concurrently. The tasks are
-    /// waited until the last engine returns.
+    /// Infoboxes are looked up alongside the results, the first one found is returned.
     #[instrument(level = "TRACE", skip_all)]
     pub async fn search(
         &self,
@@ -58,30 +98,228 @@ impl EngineHandler {
         page: u16,
         relavancy: Option<Relavancy>,
         safe_level: Option<SafeSearchLevel>,
-    ) -> (Vec<Vec<SearchResult>>, Vec<EngineError>) {
+        locale: Option<Locale>,
+    ) -> (
+        Vec<Vec<SearchResult>>,
+        Vec<EngineError>,
+        Vec<SkippedEngine>,
+        Option<Infobox>,
+    ) {
+        let mut infobox_tasks = JoinSet::new();
+
+        // Infoboxes are only shown for the first page
+        if page == 0 {
+            let health = self.health.lock().unwrap();
+            for (engine, timeout) in &self.engines {
+                if health[&engine.get_name()].is_suspended() {
+                    continue;
+                }
+                let engine = engine.clone();
+                let qclient = self.query_client.clone();
+                let query = query.clone();
+                let locale = locale.clone().filter(|_| engine.capabilities().languages);
+                let timeout = *timeout;
+
+                infobox_tasks.spawn(async move {
+                    tokio::time::timeout(timeout, engine.search_infobox(qclient, query, locale))
+                        .await
+                        .unwrap_or(Err(EngineErrorType::Timeout(timeout.as_millis())))
+                });
+            }
+        }
+
+        let (search_results, engine_errors, skipped_engines) = self
+            .dispatch(
+                Category::Text,
+                page,
+                relavancy,
+                safe_level,
+                locale,
+                |engine, qclient, locale| {
+                    let query = query.clone();
+                    async move {
+                        engine
+                            .search_text(qclient, page, query, relavancy, safe_level, locale)
+                            .await
+                    }
+                },
+            )
+            .await;
+
+        let mut infobox = None;
+        while let Some(task_status) = infobox_tasks.join_next().await {
+            match task_status {
+                Ok(Ok(Some(found))) => {
+                    infobox = Some(found);
+                    break;
+                }
+                Ok(Ok(None)) => {}
+                Ok(Err(error)) => tracing::debug!("Failed to fetch infobox: {error}"),
+                Err(error) => tracing::warn!("Infobox task failed to execute due to: \n {error}"),
+            }
+        }
+
+        (search_results, engine_errors, skipped_engines, infobox)
+    }
+
+    /// Concurrently search for images with all the selected engines.
+    #[instrument(level = "TRACE", skip_all)]
+    pub async fn search_images(
+        &self,
+        query: String,
+        page: u16,
+        relavancy: Option<Relavancy>,
+        safe_level: Option<SafeSearchLevel>,
+        locale: Option<Locale>,
+    ) -> (Vec<Vec<ImageResult>>, Vec<EngineError>, Vec<SkippedEngine>) {
+        self.dispatch(
+            Category::Images,
+            page,
+            relavancy,
+            safe_level,
+            locale,
+            |engine, qclient, locale| {
+                let query = query.clone();
+                async move {
+                    engine
+                        .search_images(qclient, page, query, relavancy, safe_level, locale)
+                        .await
+                }
+            },
+        )
+        .await
+    }
+
+    /// Concurrently search for news with all the selected engines.
+    #[instrument(level = "TRACE", skip_all)]
+    pub async fn search_news(
+        &self,
+        query: String,
+        page: u16,
+        relavancy: Option<Relavancy>,
+        safe_level: Option<SafeSearchLevel>,
+        locale: Option<Locale>,
+    ) -> (Vec<Vec<NewsResult>>, Vec<EngineError>, Vec<SkippedEngine>) {
+        self.dispatch(
+            Category::News,
+            page,
+            relavancy,
+            safe_level,
+            locale,
+            |engine, qclient, locale| {
+                let query = query.clone();
+                async move {
+                    engine
+                        .search_news(qclient, page, query, relavancy, safe_level, locale)
+                        .await
+                }
+            },
+        )
+        .await
+    }
+
+    /// Concurrently search for videos with all the selected engines.
+    #[instrument(level = "TRACE", skip_all)]
+    pub async fn search_videos(
+        &self,
+        query: String,
+        page: u16,
+        relavancy: Option<Relavancy>,
+        safe_level: Option<SafeSearchLevel>,
+        locale: Option<Locale>,
+    ) -> (Vec<Vec<VideoResult>>, Vec<EngineError>, Vec<SkippedEngine>) {
+        self.dispatch(
+            Category::Videos,
+            page,
+            relavancy,
+            safe_level,
+            locale,
+            |engine, qclient, locale| {
+                let query = query.clone();
+                async move {
+                    engine
+                        .search_videos(qclient, page, query, relavancy, safe_level, locale)
+                        .await
+                }
+            },
+        )
+        .await
+    }
+
+    /// Runs the search built by `search_fn` with every engine capable of serving it.
+    ///
+    /// An async task is spun up for every engine and is executed concurrently. The tasks are
+    /// waited until the last engine returns or its timeout elapses, whichever is earlier.
+    /// Suspended engines are not queried and are reported as errors.
+    async fn dispatch<T, F, Fut>(
+        &self,
+        category: Category,
+        page: u16,
+        relavancy: Option<Relavancy>,
+        safe_level: Option<SafeSearchLevel>,
+        locale: Option<Locale>,
+        search_fn: F,
+    ) -> (Vec<Vec<T>>, Vec<EngineError>, Vec<SkippedEngine>)
+    where
+        T: Send + 'static,
+        F: Fn(Arc<Box<dyn Engine>>, Arc<NetworkHandler>, Option<Locale>) -> Fut,
+        Fut: Future<Output = Result<Vec<T>, EngineErrorType>> + Send + 'static,
+    {
         let mut tasks = JoinSet::new();
         let mut task_ids: HashMap<Id, String> = HashMap::new();
 
-        for engine in &self.engines {
-            let engine = engine.clone();
+        let mut search_results = Vec::with_capacity(self.engines.len());
+        let mut engine_errors = Vec::with_capacity(self.engines.len());
+        let mut skipped_engines = vec![];
+
+        for (engine, timeout) in &self.engines {
             let engine_name = engine.get_name();
-            let qclient = self.query_client.clone();
-            let query = query.clone();
+            let capabilities = engine.capabilities();
+            if let Some(reason) = skip_reason(&capabilities, category, page, relavancy, safe_level)
+            {
+                tracing::debug!("Skipping {engine_name}: {reason}");
+                skipped_engines.push(SkippedEngine {
+                    engine: engine_name,
+                    reason,
+                });
+                continue;
+            }
+
+            let acquired = self
+                .health
+                .lock()
+                .unwrap()
+                .get_mut(&engine_name)
+                .unwrap()
+                .try_acquire(Instant::now());
+            if let Err(remaining) = acquired {
+                tracing::debug!("{engine_name} is suspended for {}s", remaining.as_secs());
+                engine_errors.push(EngineError {
+                    engine: engine_name,
+                    source: EngineErrorType::Suspended(remaining.as_secs()),
+                });
+                continue;
+            }
+
+            // Engines which can't restrict the results to a language are queried without it
+            let locale = locale.clone().filter(|_| capabilities.languages);
+            let search = search_fn(engine.clone(), self.query_client.clone(), locale);
+            let timeout = *timeout;
 
             let handle = tasks.spawn(async move {
-                engine
-                    .search_text(qclient, page, query, relavancy, safe_level)
+                let started = Instant::now();
+                let result = tokio::time::timeout(timeout, search)
                     .await
+                    .unwrap_or(Err(EngineErrorType::Timeout(timeout.as_millis())));
+                (result, started.elapsed())
             });
             task_ids.insert(handle.id(), engine_name);
         }
 
-        let mut search_results = Vec::with_capacity(tasks.len());
-        let mut engine_errors = Vec::with_capacity(tasks.len());
-
         while let Some(task_status) = tasks.join_next_with_id().await {
-            if let Ok((id, task_result)) = task_status {
+            if let Ok((id, (task_result, latency))) = task_status {
                 let engine = task_ids.get(&id).unwrap();
+                self.record_health(engine, &task_result, latency);
                 match task_result {
                     Ok(results) => {
                         if results.is_empty() {
@@ -104,15 +342,68 @@ impl EngineHandler {
                     }),
                 }
             } else {
-                // We can't figure out which engine failed as spawning tasks with names is currently unstable.
-                // https://docs.rs/tokio/latest/tokio/task/struct.JoinSet.html#method.build_task
-                tracing::warn!(
-                    "An engine has failed to execute due to: \n {}",
-                    task_status.err().unwrap()
-                );
+                let error = task_status.err().unwrap();
+                let engine = task_ids.get(&error.id()).unwrap();
+                tracing::warn!("{engine} has failed to execute due to: \n {error}");
+
+                // The failure still has to be recorded, otherwise a probe would never return
+                let task_result: Result<Vec<T>, _> = Err(EngineErrorType::ExecFailed);
+                self.record_health(engine, &task_result, Duration::ZERO);
+                engine_errors.push(EngineError {
+                    engine: engine.to_string(),
+                    source: EngineErrorType::ExecFailed,
+                });
             }
         }
 
-        (search_results, engine_errors)
+        (search_results, engine_errors, skipped_engines)
+    }
+
+    fn record_health<T>(
+        &self,
+        engine: &str,
+        result: &Result<Vec<T>, EngineErrorType>,
+        latency: Duration,
+    ) {
+        let mut health = self.health.lock().unwrap();
+        let health = health.get_mut(engine).unwrap();
+
+        let was_suspended = health.is_suspended();
+        health.record(result, latency, Instant::now());
+        match (was_suspended, health.is_suspended()) {
+            (false, true) => tracing::warn!("{engine} is suspended after repeated failures"),
+            (true, false) => tracing::info!("{engine} has recovered"),
+            _ => {}
+        }
+    }
+}
+
+/// Checks if an engine with the given capabilities can serve the search.
+///
+/// Engines which can't filter explicit results are skipped from searches with a safe search level
+/// of medium or higher.
+fn skip_reason(
+    capabilities: &Capabilities,
+    category: Category,
+    page: u16,
+    relavancy: Option<Relavancy>,
+    safe_level: Option<SafeSearchLevel>,
+) -> Option<SkipReason> {
+    if !capabilities.categories.contains(&category) {
+        return Some(SkipReason::UnsupportedCategory);
+    }
+    if capabilities
+        .max_page
+        .is_some_and(|max_page| page > max_page)
+    {
+        return Some(SkipReason::PageOutOfRange);
+    }
+    if !capabilities.time_filter && !matches!(relavancy, None | Some(Relavancy::AnyTime)) {
+        return Some(SkipReason::UnsupportedRelavancy);
+    }
+    if !capabilities.safe_search && safe_level.is_some_and(|level| level >= SafeSearchLevel::Medium)
+    {
+        return Some(SkipReason::UnsupportedSafeSearch);
     }
+    None
 }
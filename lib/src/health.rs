@@ -0,0 +1,350 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use crate::errors::{EngineErrorType, NetworkError};
+
+/// Number of recent searches the statistics are calculated over.
+const WINDOW_SIZE: usize = 100;
+/// Consecutive failures after which an engine is suspended.
+const FAILURE_THRESHOLD: u32 = 3;
+/// Suspension after the first trip, doubled every time the probe fails.
+const BASE_BACKOFF: Duration = Duration::from_secs(30);
+const MAX_BACKOFF: Duration = Duration::from_secs(30 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Success,
+    Ratelimited,
+    ParseFailed,
+    Failed,
+}
+
+impl Outcome {
+    /// Returns `None` for errors caused by the search itself rather than the engine, those don't
+    /// say anything about its health.
+    fn from_result<T>(result: &Result<T, EngineErrorType>) -> Option<Self> {
+        match result {
+            // The engine is working fine, there's just nothing for the query
+            Ok(_) | Err(EngineErrorType::NoResults) => Some(Outcome::Success),
+            Err(EngineErrorType::Ratelimited(_)) => Some(Outcome::Ratelimited),
+            Err(EngineErrorType::ParseFailed) => Some(Outcome::ParseFailed),
+            Err(EngineErrorType::Unsupported)
+            | Err(EngineErrorType::Network(NetworkError::UnexpectedStatus(_, 400..=499))) => None,
+            Err(_) => Some(Outcome::Failed),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum CircuitState {
+    Closed,
+    // Requests are not sent until the deadline
+    Open {
+        until: Instant,
+        backoff: Duration,
+    },
+    // A single probe request is in flight, its result decides whether the engine recovers. The
+    // probe is considered lost after the deadline.
+    HalfOpen {
+        deadline: Instant,
+        backoff: Duration,
+    },
+}
+
+/// Rolling statistics and circuit breaker of an engine.
+///
+/// After [`FAILURE_THRESHOLD`] consecutive failures, or when the engine asks to retry after some
+/// time, the circuit opens and the engine is suspended. Once the back-off elapses a single probe
+/// search is let through, the engine recovers if it succeeds, otherwise it's suspended again for
+/// twice as long.
+#[derive(Debug)]
+pub struct EngineHealth {
+    // Most recent outcomes along with the time they took, oldest first
+    window: VecDeque<(Outcome, Duration)>,
+    consecutive_failures: u32,
+    state: CircuitState,
+    // Time after which a probe which hasn't been recorded is considered lost
+    probe_timeout: Duration,
+}
+
+impl EngineHealth {
+    /// `probe_timeout` is the timeout of the engine's searches.
+    pub fn new(probe_timeout: Duration) -> Self {
+        Self {
+            window: VecDeque::with_capacity(WINDOW_SIZE),
+            consecutive_failures: 0,
+            state: CircuitState::Closed,
+            probe_timeout,
+        }
+    }
+
+    /// Checks if a search may be sent to the engine, returns the remaining suspension otherwise.
+    ///
+    /// When the suspension has elapsed the call is allowed as the half-open probe, the result of
+    /// which must be passed to [`EngineHealth::record`]. A probe which isn't recorded within the
+    /// timeout, like when the search was cancelled, is replaced by the next call.
+    pub fn try_acquire(&mut self, now: Instant) -> Result<(), Duration> {
+        match self.state {
+            CircuitState::Closed => Ok(()),
+            CircuitState::Open { until, backoff }
+            | CircuitState::HalfOpen {
+                deadline: until,
+                backoff,
+            } if now >= until => {
+                self.state = CircuitState::HalfOpen {
+                    deadline: now + self.probe_timeout,
+                    backoff,
+                };
+                Ok(())
+            }
+            CircuitState::Open { until, .. } => Err(until - now),
+            // Wait for the probe to return before letting anything else through
+            CircuitState::HalfOpen { deadline, .. } => Err(deadline - now),
+        }
+    }
+
+    pub fn is_suspended(&self) -> bool {
+        !matches!(self.state, CircuitState::Closed)
+    }
+
+    /// Records the result of a search, tripping or resetting the circuit.
+    pub fn record<T>(
+        &mut self,
+        result: &Result<T, EngineErrorType>,
+        latency: Duration,
+        now: Instant,
+    ) {
+        let Some(outcome) = Outcome::from_result(result) else {
+            // Let the next search probe the engine instead
+            if let CircuitState::HalfOpen { backoff, .. } = self.state {
+                self.state = CircuitState::Open {
+                    until: now,
+                    backoff,
+                };
+            }
+            return;
+        };
+
+        if self.window.len() == WINDOW_SIZE {
+            self.window.pop_front();
+        }
+        self.window.push_back((outcome, latency));
+
+        if outcome == Outcome::Success {
+            self.consecutive_failures = 0;
+            self.state = CircuitState::Closed;
+            return;
+        }
+
+        self.consecutive_failures += 1;
+
+        // There's no point in querying the engine before the time it asked us to wait for
+        if let Err(EngineErrorType::Ratelimited(Some(retry_after))) = result {
+            let retry_after = Duration::from_secs(*retry_after).min(MAX_BACKOFF);
+            self.state = CircuitState::Open {
+                until: now + retry_after,
+                backoff: retry_after.max(BASE_BACKOFF),
+            };
+            return;
+        }
+
+        match self.state {
+            CircuitState::HalfOpen { backoff, .. } => {
+                let backoff = (backoff * 2).min(MAX_BACKOFF);
+                self.state = CircuitState::Open {
+                    until: now + backoff,
+                    backoff,
+                };
+            }
+            CircuitState::Closed if self.consecutive_failures >= FAILURE_THRESHOLD => {
+                self.state = CircuitState::Open {
+                    until: now + BASE_BACKOFF,
+                    backoff: BASE_BACKOFF,
+                };
+            }
+            _ => {}
+        }
+    }
+
+    /// Summarizes the searches in the window.
+    pub fn stats(&self, engine: &str) -> EngineStats {
+        let count = |kind: Outcome| self.window.iter().filter(|(o, _)| *o == kind).count();
+
+        let mut latencies: Vec<Duration> = self
+            .window
+            .iter()
+            .filter(|(outcome, _)| *outcome == Outcome::Success)
+            .map(|(_, latency)| *latency)
+            .collect();
+        latencies.sort_unstable();
+        // Nearest rank percentile
+        let percentile = |p: usize| {
+            (!latencies.is_empty())
+                .then(|| latencies[(latencies.len() * p).div_ceil(100).max(1) - 1].as_millis())
+        };
+
+        EngineStats {
+            engine: engine.to_string(),
+            searches: self.window.len(),
+            success_rate: if self.window.is_empty() {
+                1.0
+            } else {
+                count(Outcome::Success) as f32 / self.window.len() as f32
+            },
+            ratelimited: count(Outcome::Ratelimited),
+            parse_failed: count(Outcome::ParseFailed),
+            latency_p50: percentile(50),
+            latency_p95: percentile(95),
+            suspended: self.is_suspended(),
+        }
+    }
+}
+
+/// Statistics of the most recent searches of an engine.
+#[derive(Debug, Serialize)]
+pub struct EngineStats {
+    pub engine: String,
+    pub searches: usize,
+    pub success_rate: f32,
+    pub ratelimited: usize,
+    pub parse_failed: usize,
+    // Latencies (in milliseconds) of successful searches
+    pub latency_p50: Option<u128>,
+    pub latency_p95: Option<u128>,
+    pub suspended: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    fn failure() -> Result<(), EngineErrorType> {
+        Err(EngineErrorType::ParseFailed)
+    }
+
+    fn trip(health: &mut EngineHealth, now: Instant) {
+        for _ in 0..FAILURE_THRESHOLD {
+            health.try_acquire(now).unwrap();
+            health.record(&failure(), Duration::ZERO, now);
+        }
+    }
+
+    #[test]
+    fn trips_after_the_failure_threshold() {
+        let mut health = EngineHealth::new(TIMEOUT);
+        let now = Instant::now();
+
+        for _ in 1..FAILURE_THRESHOLD {
+            health.record(&failure(), Duration::ZERO, now);
+        }
+        assert!(!health.is_suspended());
+
+        health.record(&failure(), Duration::ZERO, now);
+        assert!(health.is_suspended());
+        assert_eq!(health.try_acquire(now), Err(BASE_BACKOFF));
+    }
+
+    #[test]
+    fn successes_reset_the_failure_count() {
+        let mut health = EngineHealth::new(TIMEOUT);
+        let now = Instant::now();
+
+        for _ in 0..FAILURE_THRESHOLD * 2 {
+            health.record(&failure(), Duration::ZERO, now);
+            health.record(
+                &Err::<(), _>(EngineErrorType::NoResults),
+                Duration::ZERO,
+                now,
+            );
+        }
+        assert!(!health.is_suspended());
+    }
+
+    #[test]
+    fn successful_probe_closes_the_circuit() {
+        let mut health = EngineHealth::new(TIMEOUT);
+        let now = Instant::now();
+        trip(&mut health, now);
+
+        let later = now + BASE_BACKOFF;
+        assert_eq!(health.try_acquire(later), Ok(()));
+        // Only the probe is let through
+        assert!(health.try_acquire(later).is_err());
+
+        health.record(&Ok(()), Duration::ZERO, later);
+        assert!(!health.is_suspended());
+        assert_eq!(health.try_acquire(later), Ok(()));
+    }
+
+    #[test]
+    fn failed_probes_double_the_backoff() {
+        let mut health = EngineHealth::new(TIMEOUT);
+        let mut now = Instant::now();
+        trip(&mut health, now);
+
+        let mut backoff = BASE_BACKOFF;
+        while backoff < MAX_BACKOFF {
+            now += backoff;
+            health.try_acquire(now).unwrap();
+            health.record(&failure(), Duration::ZERO, now);
+
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+            assert_eq!(health.try_acquire(now), Err(backoff));
+        }
+    }
+
+    #[test]
+    fn lost_probes_are_replaced_after_the_timeout() {
+        let mut health = EngineHealth::new(TIMEOUT);
+        let now = Instant::now();
+        trip(&mut health, now);
+
+        let probe = now + BASE_BACKOFF;
+        health.try_acquire(probe).unwrap();
+        assert!(health.try_acquire(probe + TIMEOUT / 2).is_err());
+        assert_eq!(health.try_acquire(probe + TIMEOUT), Ok(()));
+    }
+
+    #[test]
+    fn retry_after_suspends_immediately() {
+        let mut health = EngineHealth::new(TIMEOUT);
+        let now = Instant::now();
+
+        health.record(
+            &Err::<(), _>(EngineErrorType::Ratelimited(Some(120))),
+            Duration::ZERO,
+            now,
+        );
+        assert_eq!(health.try_acquire(now), Err(Duration::from_secs(120)));
+    }
+
+    #[test]
+    fn errors_caused_by_the_search_are_ignored() {
+        let mut health = EngineHealth::new(TIMEOUT);
+        let now = Instant::now();
+
+        for _ in 0..FAILURE_THRESHOLD {
+            health.record(
+                &Err::<(), _>(EngineErrorType::Network(NetworkError::UnexpectedStatus(
+                    "example.com".to_string(),
+                    400,
+                ))),
+                Duration::ZERO,
+                now,
+            );
+            health.record(
+                &Err::<(), _>(EngineErrorType::Unsupported),
+                Duration::ZERO,
+                now,
+            );
+        }
+        assert!(!health.is_suspended());
+        assert_eq!(health.stats("Example").searches, 0);
+    }
+}
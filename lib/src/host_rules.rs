@@ -0,0 +1,107 @@
+use anyhow::{ensure, Context, Result};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::domain_list::DomainList;
+
+/// A rule applied to the results from matching hosts, read from the config file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostRule {
+    /// Hosts the rule applies to, in the format of a [`DomainList`] entry.
+    pub host: String,
+    #[serde(flatten)]
+    pub action: HostAction,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum HostAction {
+    /// Replaces the host of the url, like with a privacy respecting front-end.
+    Rewrite { to: String },
+    /// Multiplies the score of the result.
+    Boost { factor: f32 },
+    /// Divides the score of the result.
+    Penalize { factor: f32 },
+    /// Drops the result, like the block list.
+    Hide,
+}
+
+impl HostAction {
+    /// Multiplier applied to the score given to the result by each engine.
+    pub fn score_factor(&self) -> f32 {
+        match self {
+            HostAction::Boost { factor } => *factor,
+            HostAction::Penalize { factor } => 1.0 / factor,
+            HostAction::Rewrite { .. } | HostAction::Hide => 1.0,
+        }
+    }
+}
+
+/// Host rules along with their parsed host patterns.
+#[derive(Debug, Default)]
+pub struct HostRules {
+    rules: Vec<(DomainList, HostRule)>,
+}
+
+impl HostRules {
+    pub fn new(rules: Vec<HostRule>) -> Result<Self> {
+        let rules = rules
+            .into_iter()
+            .map(|rule| {
+                let hosts = DomainList::parse(&rule.host)
+                    .with_context(|| format!("Invalid host rule for {}", rule.host))?;
+
+                match &rule.action {
+                    HostAction::Rewrite { to } => {
+                        Url::parse("https://example.com")?
+                            .set_host(Some(to))
+                            .with_context(|| format!("Invalid host {to} to rewrite to"))?;
+                    }
+                    HostAction::Boost { factor } | HostAction::Penalize { factor } => {
+                        ensure!(
+                            *factor > 0.0,
+                            "Factor of the host rule for {} must be positive",
+                            rule.host
+                        );
+                    }
+                    HostAction::Hide => {}
+                }
+
+                Ok((hosts, rule))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self { rules })
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Rules matching the host of the url.
+    pub fn matching(&self, url: &Url) -> Vec<HostRule> {
+        self.rules
+            .iter()
+            .filter(|(hosts, _)| hosts.is_match(url))
+            .map(|(_, rule)| rule.clone())
+            .collect()
+    }
+
+    /// Replaces the host of the url with the one of the last rewrite rule among the matching
+    /// `rules`.
+    pub fn rewrite(url: &mut Url, rules: &[HostRule]) {
+        let rewrite = rules.iter().rev().find_map(|rule| match &rule.action {
+            HostAction::Rewrite { to } => Some(to),
+            _ => None,
+        });
+
+        if let Some(to) = rewrite {
+            // The host was validated when the rules were parsed
+            let _ = url.set_host(Some(to));
+        }
+    }
+}
@@ -1,31 +1,65 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt::Display, str::FromStr};
 
 use aggregator::Aggregator;
-use anyhow::Result;
-use errors::EngineError;
-use handler::EngineHandler;
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use domain_list::DomainList;
+use errors::{EngineError, SkippedEngine};
+use handler::{EngineHandler, EngineSettings};
+use health::EngineStats;
+use host_rules::{HostRule, HostRules};
 use network::NetworkHandler;
+use ranking::Ranking;
+use registry::EngineRegistry;
+use safe_search::SafeSearchFilter;
 
 mod aggregator;
-mod engines;
+mod canonical;
+pub mod domain_list;
+pub mod engines;
 pub mod errors;
 pub mod handler;
-mod network;
+pub mod health;
+pub mod host_rules;
+mod near_duplicate;
+pub mod network;
+pub mod ranking;
+pub mod registry;
+mod safe_search;
+
+pub use engines::selector::SelectorDefinition;
 
 use serde::{Deserialize, Serialize};
 use url::Url;
 
-#[derive(Debug, Copy, Clone, Deserialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
 pub enum SafeSearchLevel {
     // No filtering
     Off,
+    // The search engines will be requested to apply their lightest filtering
+    Low,
     // The search engines will be requested to filter the results
     Medium,
     // `SafeSearchLevel::Medium` + internal allowlists and blocklists
     High,
 }
+
+impl TryFrom<u8> for SafeSearchLevel {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        match value {
+            0 => Ok(SafeSearchLevel::Off),
+            1 => Ok(SafeSearchLevel::Low),
+            2 => Ok(SafeSearchLevel::Medium),
+            3 => Ok(SafeSearchLevel::High),
+            _ => bail!("Safe search level must be between 0 and 3, got {value}"),
+        }
+    }
+}
+
 /// Time Relavancy of query
-#[derive(Debug, Copy, Clone, Deserialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
 pub enum Relavancy {
     AnyTime,
     PastDay,
@@ -34,23 +68,131 @@ pub enum Relavancy {
     PastYear,
 }
 
+impl Relavancy {
+    /// Maximum age of a result which is relavant.
+    pub fn max_age(&self) -> Option<chrono::Duration> {
+        match self {
+            Relavancy::AnyTime => None,
+            Relavancy::PastDay => Some(chrono::Duration::days(1)),
+            Relavancy::PastWeek => Some(chrono::Duration::weeks(1)),
+            Relavancy::PastMonth => Some(chrono::Duration::days(30)),
+            Relavancy::PastYear => Some(chrono::Duration::days(365)),
+        }
+    }
+}
+
+/// Order in which results are sorted
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SortOrder {
+    #[default]
+    Relevance,
+    // Newest first, only applies to results with a publication date
+    Recency,
+}
+
+/// Kind of results to search for
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Category {
+    #[default]
+    Text,
+    Images,
+    News,
+    Videos,
+}
+
+/// Language and optionally the region the results should be in, like `en-US`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Locale {
+    // Lowercase ISO 639-1 code
+    pub language: String,
+    // Uppercase ISO 3166-1 code
+    pub region: Option<String>,
+}
+
+impl Locale {
+    /// Picks the first locale from an `Accept-Language` header, which is the most preferred one.
+    pub fn from_accept_language(header: &str) -> Option<Self> {
+        header
+            .split(',')
+            .filter_map(|tag| tag.split(';').next())
+            .find_map(|tag| tag.trim().parse().ok())
+    }
+}
+
+impl FromStr for Locale {
+    type Err = anyhow::Error;
+
+    /// Parses tags like `en`, `en-US` or `en_us`.
+    fn from_str(tag: &str) -> Result<Self> {
+        let mut parts = tag.split(['-', '_']);
+        let language = parts.next().unwrap_or_default();
+        let region = parts.next();
+
+        if !(2..=3).contains(&language.len()) || !language.chars().all(|c| c.is_ascii_alphabetic())
+        {
+            bail!("Invalid language {language}");
+        }
+        if let Some(region) = region {
+            if region.len() != 2 || !region.chars().all(|c| c.is_ascii_alphabetic()) {
+                bail!("Invalid region {region}");
+            }
+        }
+
+        Ok(Self {
+            language: language.to_lowercase(),
+            region: region.map(|region| region.to_uppercase()),
+        })
+    }
+}
+
+impl Display for Locale {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.region {
+            Some(region) => write!(f, "{}-{region}", self.language),
+            None => write!(f, "{}", self.language),
+        }
+    }
+}
+
+/// Title and description of a result as returned by one of the engines which suggested it.
+#[derive(Debug, Clone, Serialize)]
+pub struct Snippet {
+    pub source: String,
+    pub title: String,
+    pub description: String,
+}
+
+impl Snippet {
+    fn new(title: &str, description: &str, source: &str) -> Self {
+        Self {
+            source: source.to_string(),
+            title: title.to_string(),
+            description: description.to_string(),
+        }
+    }
+}
+
 // Search result returned by an engine
 #[derive(Debug, Serialize)]
 pub struct SearchResult {
     pub url: Url,
+    // Most informative title and description among the snippets
     pub title: String,
     pub description: String,
     pub score: f32,
     // List of search engines which suggested this result
     pub sources: Vec<String>,
+    pub snippets: Vec<Snippet>,
+    // Other copies of the page, like its AMP or mobile version and mirrors
+    pub alternates: Vec<Url>,
+    // Host rules which changed the url or the score of the result
+    pub host_rules: Vec<HostRule>,
 }
 
 impl PartialEq for SearchResult {
+    /// Results are equal when they link to the same page, see [`canonical::canonical_key`].
     fn eq(&self, other: &Self) -> bool {
-        // Ignore transport type as it always will http or https
-        self.url.host().unwrap() == other.url.host().unwrap()
-            && self.url.path() == other.url.path()
-            && self.url.query() == other.url.query()
+        canonical::canonical_key(&self.url) == canonical::canonical_key(&other.url)
     }
 }
 
@@ -62,58 +204,414 @@ impl SearchResult {
             description: description.to_string(),
             score: 0.0,
             sources: vec![source.to_string()],
+            snippets: vec![Snippet::new(title, description, source)],
+            alternates: Vec::new(),
+            host_rules: Vec::new(),
+        })
+    }
+}
+
+// Image search result returned by an engine
+#[derive(Debug, Serialize)]
+pub struct ImageResult {
+    // Url of the full size image
+    pub url: Url,
+    pub thumbnail: Url,
+    // Url of the page containing the image
+    pub page_url: Url,
+    pub title: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub score: f32,
+    // List of search engines which suggested this result
+    pub sources: Vec<String>,
+    // Host rules which changed the url or the score of the result
+    pub host_rules: Vec<HostRule>,
+}
+
+impl ImageResult {
+    pub fn new(
+        url: &str,
+        thumbnail: &str,
+        page_url: &str,
+        title: &str,
+        source: &str,
+    ) -> Result<Self> {
+        Ok(Self {
+            url: Url::parse(url)?,
+            thumbnail: Url::parse(thumbnail)?,
+            page_url: Url::parse(page_url)?,
+            title: title.to_string(),
+            width: None,
+            height: None,
+            score: 0.0,
+            sources: vec![source.to_string()],
+            host_rules: Vec::new(),
         })
     }
+
+    pub fn with_dimensions(mut self, width: u32, height: u32) -> Self {
+        self.width = Some(width);
+        self.height = Some(height);
+        self
+    }
+}
+
+// News article returned by an engine
+#[derive(Debug, Serialize)]
+pub struct NewsResult {
+    pub url: Url,
+    // Most informative title and description among the snippets
+    pub title: String,
+    pub description: String,
+    pub publisher: Option<String>,
+    pub published_at: Option<DateTime<Utc>>,
+    pub thumbnail: Option<Url>,
+    pub score: f32,
+    // List of search engines which suggested this result
+    pub sources: Vec<String>,
+    pub snippets: Vec<Snippet>,
+    // Other copies of the page, like its AMP or mobile version and mirrors
+    pub alternates: Vec<Url>,
+    // Host rules which changed the url or the score of the result
+    pub host_rules: Vec<HostRule>,
+}
+
+impl NewsResult {
+    pub fn new(url: &str, title: &str, description: &str, source: &str) -> Result<Self> {
+        Ok(Self {
+            url: Url::parse(url)?,
+            title: title.to_string(),
+            description: description.to_string(),
+            publisher: None,
+            published_at: None,
+            thumbnail: None,
+            score: 0.0,
+            sources: vec![source.to_string()],
+            snippets: vec![Snippet::new(title, description, source)],
+            alternates: Vec::new(),
+            host_rules: Vec::new(),
+        })
+    }
+
+    pub fn with_publisher(mut self, publisher: &str) -> Self {
+        self.publisher = Some(publisher.to_string());
+        self
+    }
+
+    pub fn with_published_at(mut self, published_at: DateTime<Utc>) -> Self {
+        self.published_at = Some(published_at);
+        self
+    }
+
+    pub fn with_thumbnail(mut self, thumbnail: &str) -> Self {
+        self.thumbnail = Url::parse(thumbnail).ok();
+        self
+    }
+}
+
+// Video returned by an engine
+#[derive(Debug, Serialize)]
+pub struct VideoResult {
+    pub url: Url,
+    // Most informative title and description among the snippets
+    pub title: String,
+    pub description: String,
+    pub thumbnail: Option<Url>,
+    // Length of the video in seconds
+    pub duration: Option<u64>,
+    pub channel: Option<String>,
+    pub published_at: Option<DateTime<Utc>>,
+    pub score: f32,
+    // List of search engines which suggested this result
+    pub sources: Vec<String>,
+    pub snippets: Vec<Snippet>,
+    // Other copies of the page, like its AMP or mobile version and mirrors
+    pub alternates: Vec<Url>,
+    // Host rules which changed the url or the score of the result
+    pub host_rules: Vec<HostRule>,
+}
+
+impl VideoResult {
+    pub fn new(url: &str, title: &str, description: &str, source: &str) -> Result<Self> {
+        Ok(Self {
+            url: Url::parse(url)?,
+            title: title.to_string(),
+            description: description.to_string(),
+            thumbnail: None,
+            duration: None,
+            channel: None,
+            published_at: None,
+            score: 0.0,
+            sources: vec![source.to_string()],
+            snippets: vec![Snippet::new(title, description, source)],
+            alternates: Vec::new(),
+            host_rules: Vec::new(),
+        })
+    }
+
+    pub fn with_thumbnail(mut self, thumbnail: &str) -> Self {
+        self.thumbnail = Url::parse(thumbnail).ok();
+        self
+    }
+
+    pub fn with_duration(mut self, duration: u64) -> Self {
+        self.duration = Some(duration);
+        self
+    }
+
+    pub fn with_channel(mut self, channel: &str) -> Self {
+        self.channel = Some(channel.to_string());
+        self
+    }
+
+    pub fn with_published_at(mut self, published_at: DateTime<Utc>) -> Self {
+        self.published_at = Some(published_at);
+        self
+    }
+
+    /// Formats the duration as `h:mm:ss`, or `m:ss` for videos shorter than an hour.
+    pub fn formatted_duration(&self) -> Option<String> {
+        let duration = self.duration?;
+        let (hours, minutes, seconds) = (duration / 3600, duration / 60 % 60, duration % 60);
+
+        if hours > 0 {
+            Some(format!("{hours}:{minutes:02}:{seconds:02}"))
+        } else {
+            Some(format!("{minutes}:{seconds:02}"))
+        }
+    }
+}
+
+/// Summary of the topic of a query, shown alongside the results.
+#[derive(Debug, Serialize)]
+pub struct Infobox {
+    pub title: String,
+    pub extract: String,
+    pub thumbnail: Option<Url>,
+    pub url: Url,
+    // Search engine which provided the summary
+    pub source: String,
 }
 
 #[derive(Serialize, Debug)]
 pub struct QueryResult {
     pub query: String,
+    pub category: Category,
     pub results: Vec<SearchResult>,
+    pub images: Vec<ImageResult>,
+    pub news: Vec<NewsResult>,
+    pub videos: Vec<VideoResult>,
+    pub infobox: Option<Infobox>,
     pub errors: Vec<EngineError>,
+    // Engines which were not queried as they can't serve the search
+    pub skipped_engines: Vec<SkippedEngine>,
+    // Number of results dropped by the block list
+    pub blocked_results: usize,
+    // Number of results hidden by host rules
+    pub hidden_results: usize,
+}
+
+/// Options for a single search, the defaults are used for anything not set by the user.
+#[derive(Debug, Default)]
+pub struct SearchOptions {
+    pub page: u16,
+    pub relavancy: Option<Relavancy>,
+    // Falls back to the level from the config when not set
+    pub safe_level: Option<SafeSearchLevel>,
+    pub category: Category,
+    pub sort: SortOrder,
+    pub locale: Option<Locale>,
+}
+
+/// Configuration used to initialise a [`Handler`].
+#[derive(Debug)]
+pub struct HandlerConfig {
+    /// Scores the results of the engines, see [`ranking::RankingAlgorithm`] for the built-in ones.
+    pub ranking: Box<dyn Ranking>,
+    pub engine_score_multipliers: HashMap<String, f32>,
+    /// Connect timeout (in seconds) for the requests made to upstream engines.
+    pub timeout: u16,
+    pub proxy_url: Option<String>,
+    pub is_tor: Option<bool>,
+    /// Engines to search with, along with their settings.
+    pub engines: HashMap<String, EngineSettings>,
+    /// Used to build the engines, register custom engines here.
+    pub registry: EngineRegistry,
+    pub user_agents: Vec<String>,
+    /// Used when a search doesn't specify a safe search level.
+    pub safe_search_level: SafeSearchLevel,
+    /// Results matching this list are pinned above the rest.
+    pub allow_list: DomainList,
+    /// Results matching this list are dropped.
+    pub block_list: DomainList,
+    /// Collapse results linking to copies of the same page, like AMP pages and mirrors.
+    pub collapse_near_duplicates: bool,
+    /// Rewrite, boost, penalize or hide the results from matching hosts.
+    pub host_rules: HostRules,
 }
 
 pub struct Handler {
     aggregator: Aggregator,
     engine_handler: EngineHandler,
+    safe_search_filter: SafeSearchFilter,
+    // Used when a search doesn't specify a safe search level
+    safe_search_level: SafeSearchLevel,
 }
 
 impl Handler {
-    pub async fn new(
-        engine_score_multipliers: HashMap<String, f32>,
-        timeout: u16,
-        proxy_url: Option<&str>,
-        is_tor: Option<bool>,
-        engines: &[String],
-        user_agents: Vec<String>,
-    ) -> Result<Self> {
-        let aggregator = Aggregator::new(engine_score_multipliers);
-        let network_handler = NetworkHandler::new(timeout, proxy_url, is_tor, user_agents).await?;
-        let engine_handler = EngineHandler::new(engines, network_handler)?;
+    pub async fn new(config: HandlerConfig) -> Result<Self> {
+        let aggregator = Aggregator::new(
+            config.ranking,
+            config.engine_score_multipliers,
+            config.allow_list,
+            config.block_list,
+            config.collapse_near_duplicates,
+            config.host_rules,
+        );
+        let network_handler = NetworkHandler::new(
+            config.timeout,
+            config.proxy_url.as_deref(),
+            config.is_tor,
+            config.user_agents,
+        )
+        .await?;
+        let engine_handler =
+            EngineHandler::new(&config.engines, &config.registry, network_handler)?;
 
         Ok(Self {
             aggregator,
             engine_handler,
+            safe_search_filter: SafeSearchFilter::new(),
+            safe_search_level: config.safe_search_level,
         })
     }
 
-    pub async fn search(
-        &self,
-        query: String,
-        page: u16,
-        relavancy: Option<Relavancy>,
-        safe_level: Option<SafeSearchLevel>,
-    ) -> QueryResult {
-        let (raw_results, errors) = self
-            .engine_handler
-            .search(query.clone(), page, relavancy, safe_level)
-            .await;
-
-        let results = self.aggregator.process(raw_results);
-        QueryResult {
-            query,
-            results,
-            errors,
+    /// Statistics of the recent searches of every engine.
+    pub fn engine_stats(&self) -> Vec<EngineStats> {
+        self.engine_handler.stats()
+    }
+
+    pub async fn search(&self, query: String, options: SearchOptions) -> QueryResult {
+        let SearchOptions {
+            page,
+            relavancy,
+            safe_level,
+            category,
+            sort,
+            locale,
+        } = options;
+        let safe_level = safe_level.unwrap_or(self.safe_search_level);
+
+        let mut query_result = QueryResult {
+            query: query.clone(),
+            category,
+            results: vec![],
+            images: vec![],
+            news: vec![],
+            videos: vec![],
+            infobox: None,
+            errors: vec![],
+            skipped_engines: vec![],
+            blocked_results: 0,
+            hidden_results: 0,
+        };
+
+        match category {
+            Category::Text => {
+                let (raw_results, errors, skipped_engines, infobox) = self
+                    .engine_handler
+                    .search(query, page, relavancy, Some(safe_level), locale)
+                    .await;
+
+                let (mut results, blocked_results, hidden_results) =
+                    self.aggregator.process(raw_results);
+                self.aggregator.collapse_near_duplicates(&mut results);
+                if safe_level >= SafeSearchLevel::High {
+                    self.safe_search_filter.filter(&mut results);
+                }
+
+                self.aggregator.apply_rewrites(&mut results);
+                query_result.results = results;
+                query_result.infobox = infobox;
+                query_result.errors = errors;
+                query_result.skipped_engines = skipped_engines;
+                query_result.blocked_results = blocked_results;
+                query_result.hidden_results = hidden_results;
+            }
+            Category::Images => {
+                let (raw_results, errors, skipped_engines) = self
+                    .engine_handler
+                    .search_images(query, page, relavancy, Some(safe_level), locale)
+                    .await;
+
+                let (mut images, blocked_results, hidden_results) =
+                    self.aggregator.process(raw_results);
+                if safe_level >= SafeSearchLevel::High {
+                    self.safe_search_filter.filter(&mut images);
+                }
+
+                self.aggregator.apply_rewrites(&mut images);
+                query_result.images = images;
+                query_result.errors = errors;
+                query_result.skipped_engines = skipped_engines;
+                query_result.blocked_results = blocked_results;
+                query_result.hidden_results = hidden_results;
+            }
+            Category::News => {
+                let (raw_results, errors, skipped_engines) = self
+                    .engine_handler
+                    .search_news(query, page, relavancy, Some(safe_level), locale)
+                    .await;
+
+                let (mut news, blocked_results, hidden_results) =
+                    self.aggregator.process(raw_results);
+                self.aggregator.collapse_near_duplicates(&mut news);
+                if safe_level >= SafeSearchLevel::High {
+                    self.safe_search_filter.filter(&mut news);
+                }
+                if let Some(relavancy) = relavancy {
+                    self.aggregator.filter_by_relavancy(&mut news, relavancy);
+                }
+                if sort == SortOrder::Recency {
+                    self.aggregator.sort_by_recency(&mut news);
+                }
+
+                self.aggregator.apply_rewrites(&mut news);
+                query_result.news = news;
+                query_result.errors = errors;
+                query_result.skipped_engines = skipped_engines;
+                query_result.blocked_results = blocked_results;
+                query_result.hidden_results = hidden_results;
+            }
+            Category::Videos => {
+                let (raw_results, errors, skipped_engines) = self
+                    .engine_handler
+                    .search_videos(query, page, relavancy, Some(safe_level), locale)
+                    .await;
+
+                let (mut videos, blocked_results, hidden_results) =
+                    self.aggregator.process(raw_results);
+                self.aggregator.collapse_near_duplicates(&mut videos);
+                if safe_level >= SafeSearchLevel::High {
+                    self.safe_search_filter.filter(&mut videos);
+                }
+                if let Some(relavancy) = relavancy {
+                    self.aggregator.filter_by_relavancy(&mut videos, relavancy);
+                }
+                if sort == SortOrder::Recency {
+                    self.aggregator.sort_by_recency(&mut videos);
+                }
+
+                self.aggregator.apply_rewrites(&mut videos);
+                query_result.videos = videos;
+                query_result.errors = errors;
+                query_result.skipped_engines = skipped_engines;
+                query_result.blocked_results = blocked_results;
+                query_result.hidden_results = hidden_results;
+            }
         }
+
+        query_result
     }
 }
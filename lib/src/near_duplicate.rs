@@ -0,0 +1,285 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+use url::Url;
+
+use crate::canonical::canonical_key;
+
+/// Subdomains serving the mobile or AMP version of a site.
+const VARIANT_SUBDOMAINS: &[&str] = &["m.", "mobile.", "amp."];
+/// Google serves cached AMP pages from `<site>.cdn.ampproject.org/c/s/<host>/<path>`.
+const AMP_CACHE_HOST: &str = ".cdn.ampproject.org";
+/// Query parameters requesting the AMP version of a page, `amp` with other values can be an
+/// unrelated parameter.
+const AMP_PARAMS: &[&str] = &["amp", "amp=1", "amp=true", "outputType=amp"];
+/// Texts with fewer words are too short to be compared reliably.
+const MIN_WORDS: usize = 8;
+/// Maximum number of differing bits between the simhashes of near-duplicates.
+const MAX_DISTANCE: u32 = 3;
+
+/// Identifies the page a result links to and the content of its snippet, to find copies of the
+/// same page under different urls.
+#[derive(Debug)]
+pub struct Fingerprint {
+    // Canonical url with the AMP and mobile markers removed
+    page: String,
+    // Whether the url is the AMP or mobile version of the page
+    variant: bool,
+    simhash: Option<u64>,
+}
+
+impl Fingerprint {
+    pub fn new(url: &Url, title: &str, description: &str) -> Self {
+        let page = page_key(url);
+
+        Self {
+            variant: page != canonical_key(url),
+            page,
+            simhash: simhash(&format!("{title} {description}")),
+        }
+    }
+
+    /// Results are near-duplicates when they link to versions of the same page, or when their
+    /// titles and descriptions are almost the same, like those of mirrors and syndicated articles.
+    pub fn is_near_duplicate(&self, other: &Fingerprint) -> bool {
+        if self.page == other.page {
+            return true;
+        }
+
+        match (self.simhash, other.simhash) {
+            (Some(hash), Some(other_hash)) => (hash ^ other_hash).count_ones() <= MAX_DISTANCE,
+            _ => false,
+        }
+    }
+
+    /// Whether the url is the AMP or mobile version of the page.
+    pub fn is_variant(&self) -> bool {
+        self.variant
+    }
+}
+
+/// Canonical url of the page after removing the markers of its AMP and mobile versions.
+fn page_key(url: &Url) -> String {
+    let mut url = url.clone();
+
+    if let Some(path) = url
+        .host_str()
+        .filter(|host| host.ends_with(AMP_CACHE_HOST))
+        .and_then(|_| url.path().strip_prefix("/c/"))
+        .map(str::to_string)
+    {
+        let path = path.strip_prefix("s/").unwrap_or(&path);
+        if let Ok(original) = Url::parse(&format!("https://{path}")) {
+            url = original;
+        }
+    }
+
+    if let Some(host) = url.host_str().and_then(|host| {
+        VARIANT_SUBDOMAINS
+            .iter()
+            .find_map(|subdomain| host.strip_prefix(subdomain))
+            .map(str::to_string)
+    }) {
+        // Fails for hosts which aren't domains, those keep the subdomain
+        let _ = url.set_host(Some(&host));
+    }
+
+    // Only a trailing `/amp` segment or `.amp` suffix marks the AMP version, other `amp` segments
+    // can be part of the address of the page
+    let path = url.path().trim_end_matches('/');
+    if let Some(path) = path
+        .strip_suffix("/amp")
+        .or_else(|| path.strip_suffix(".amp"))
+        .filter(|path| !path.is_empty() && !path.ends_with('/'))
+        .map(str::to_string)
+    {
+        url.set_path(&path);
+    }
+
+    let query = url
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .filter(|param| !AMP_PARAMS.contains(param))
+        .collect::<Vec<_>>()
+        .join("&");
+    url.set_query((!query.is_empty()).then_some(&query));
+
+    canonical_key(&url)
+}
+
+/// SimHash of the word pairs of a text, similar texts have hashes differing in few bits.
+fn simhash(text: &str) -> Option<u64> {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    if words.len() < MIN_WORDS {
+        return None;
+    }
+
+    let mut weights = [0i32; 64];
+    for pair in words.windows(2) {
+        let mut hasher = DefaultHasher::new();
+        pair.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            if hash & (1 << bit) != 0 {
+                *weight += 1;
+            } else {
+                *weight -= 1;
+            }
+        }
+    }
+
+    Some(
+        weights
+            .iter()
+            .enumerate()
+            .filter(|(_, weight)| **weight > 0)
+            .fold(0, |hash, (bit, _)| hash | (1 << bit)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fingerprint(url: &str) -> Fingerprint {
+        Fingerprint::new(&Url::parse(url).unwrap(), "", "")
+    }
+
+    fn same_page(url: &str, other: &str) -> bool {
+        fingerprint(url).is_near_duplicate(&fingerprint(other))
+    }
+
+    #[test]
+    fn amp_cache_urls_are_the_page() {
+        assert!(same_page(
+            "https://example-com.cdn.ampproject.org/c/s/example.com/news/story",
+            "https://example.com/news/story"
+        ));
+        // Pages cached over http don't have the `s/` prefix
+        assert!(same_page(
+            "https://example-com.cdn.ampproject.org/c/example.com/news/story",
+            "https://example.com/news/story"
+        ));
+    }
+
+    #[test]
+    fn amp_and_mobile_versions_are_the_page() {
+        assert!(same_page(
+            "https://m.example.com/a",
+            "https://example.com/a"
+        ));
+        assert!(same_page(
+            "https://amp.example.com/a",
+            "https://www.example.com/a"
+        ));
+        assert!(same_page(
+            "https://example.com/news/story/amp",
+            "https://example.com/news/story"
+        ));
+        assert!(same_page(
+            "https://example.com/news/story.amp",
+            "https://example.com/news/story"
+        ));
+        assert!(same_page(
+            "https://example.com/a?amp=1&id=2",
+            "https://example.com/a?id=2"
+        ));
+        assert!(same_page(
+            "https://example.com/a?outputType=amp",
+            "https://example.com/a"
+        ));
+
+        assert!(fingerprint("https://m.example.com/a").is_variant());
+        assert!(!fingerprint("https://example.com/a").is_variant());
+    }
+
+    #[test]
+    fn amp_elsewhere_in_the_url_is_kept() {
+        assert!(!same_page(
+            "https://guitar.com/amp/reviews",
+            "https://guitar.com/reviews"
+        ));
+        assert!(!same_page(
+            "https://example.com/amp",
+            "https://example.com/"
+        ));
+        assert!(!same_page(
+            "https://example.com/amp/reviews",
+            "https://example.com/reviews"
+        ));
+        assert!(!same_page(
+            "https://example.com/a?amp=50",
+            "https://example.com/a"
+        ));
+        assert!(!same_page(
+            "https://mail.example.com/a",
+            "https://example.com/a"
+        ));
+    }
+
+    #[test]
+    fn similar_snippets_are_near_duplicates() {
+        let title = "Rust 1.80 released with lazy cells and exclusive ranges";
+        let description = "The Rust team is happy to announce a new version of Rust, 1.80.0. \
+            Rust is a programming language empowering everyone to build reliable software.";
+        let original = Fingerprint::new(
+            &Url::parse("https://blog.rust-lang.org/2024/07/25/Rust-1.80.0.html").unwrap(),
+            title,
+            description,
+        );
+
+        let mirror = Fingerprint::new(
+            &Url::parse("https://mirror.example.com/rust-1-80").unwrap(),
+            title,
+            description,
+        );
+        assert!(original.is_near_duplicate(&mirror));
+
+        let other = Fingerprint::new(
+            &Url::parse("https://example.com/python-3-13").unwrap(),
+            "Python 3.13 released with a new interactive interpreter",
+            "The Python team announces Python 3.13.0, with an experimental free-threaded \
+            build mode and a just-in-time compiler.",
+        );
+        assert!(!original.is_near_duplicate(&other));
+    }
+
+    #[test]
+    fn simhash_threshold() {
+        let hash = simhash("one two three four five six seven eight nine ten").unwrap();
+
+        let close = Fingerprint {
+            page: "a".to_string(),
+            variant: false,
+            simhash: Some(hash),
+        };
+        let at_threshold = Fingerprint {
+            page: "b".to_string(),
+            variant: false,
+            simhash: Some(hash ^ 0b111),
+        };
+        let past_threshold = Fingerprint {
+            page: "c".to_string(),
+            variant: false,
+            simhash: Some(hash ^ 0b1111),
+        };
+        assert!(close.is_near_duplicate(&at_threshold));
+        assert!(!close.is_near_duplicate(&past_threshold));
+    }
+
+    #[test]
+    fn short_snippets_are_not_compared() {
+        assert_eq!(simhash("Home page"), None);
+        let home = Fingerprint::new(&Url::parse("https://a.example/").unwrap(), "Home", "");
+        let other = Fingerprint::new(&Url::parse("https://b.example/").unwrap(), "Home", "");
+        assert!(!home.is_near_duplicate(&other));
+    }
+}
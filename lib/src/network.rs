@@ -2,9 +2,10 @@ use std::time::Duration;
 
 use crate::errors::NetworkError;
 use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
 use reqwest::{
-    header::{HeaderMap, USER_AGENT},
-    Client, Proxy,
+    header::{HeaderMap, ACCEPT, RETRY_AFTER, USER_AGENT},
+    Client, Proxy, StatusCode,
 };
 
 #[derive(Debug)]
@@ -29,7 +30,8 @@ impl NetworkHandler {
             let client = client.proxy(proxy);
 
             let client = client.build().with_context(|| {
-                format!("Failed to initialise web query engine. Check for configuration mistakes")
+                "Failed to initialise web query engine. Check for configuration mistakes"
+                    .to_string()
             })?;
 
             // TODO: Check tor connection every x seconds to ensure integrity.
@@ -39,7 +41,8 @@ impl NetworkHandler {
             client
         } else {
             client.build().with_context(|| {
-                format!("Failed to initialise web query engine. Check for configuration mistakes")
+                "Failed to initialise web query engine. Check for configuration mistakes"
+                    .to_string()
             })?
         };
 
@@ -63,7 +66,8 @@ impl NetworkHandler {
 
     /// Fetches a url with `GET` method.
     ///
-    /// The useragent can be overriden by setting the useragent header.
+    /// The useragent can be overriden by setting the useragent header. When `is_json` is set, json
+    /// is requested from the server and the body is returned as is for the caller to deserialize.
     pub async fn get_data(
         &self,
         url: &str,
@@ -72,15 +76,39 @@ impl NetworkHandler {
     ) -> Result<String, NetworkError> {
         let user_agent = &self.user_agents[fastrand::usize(..self.user_agents.len())];
         headers.insert(USER_AGENT, user_agent.parse().unwrap());
+        if is_json {
+            headers.insert(ACCEPT, "application/json".parse().unwrap());
+        }
 
         let data = self.client.get(url).headers(headers).send().await?;
 
-        tracing::trace!("Request to {url} returned {}", data.status());
+        let status = data.status();
+        tracing::trace!("Request to {url} returned {status}");
 
-        if is_json {
-            Ok(data.json().await?)
-        } else {
-            Ok(data.text().await?)
+        // Only the host is reported as the url contains the query
+        let host = data.url().host_str().unwrap_or_default().to_string();
+        match status {
+            StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE => Err(
+                NetworkError::Ratelimited(NetworkHandler::retry_after(data.headers())),
+            ),
+            StatusCode::FORBIDDEN => Err(NetworkError::Blocked(host)),
+            status if status.is_server_error() => {
+                Err(NetworkError::Upstream(host, status.as_u16()))
+            }
+            status if !status.is_success() => {
+                Err(NetworkError::UnexpectedStatus(host, status.as_u16()))
+            }
+            _ => Ok(data.text().await?),
         }
     }
+
+    /// Parses the `Retry-After` header, which is either the seconds to wait or a date.
+    fn retry_after(headers: &HeaderMap) -> Option<u64> {
+        let retry_after = headers.get(RETRY_AFTER)?.to_str().ok()?;
+
+        retry_after.parse().ok().or_else(|| {
+            let date = DateTime::parse_from_rfc2822(retry_after).ok()?;
+            u64::try_from((date.with_timezone(&Utc) - Utc::now()).num_seconds()).ok()
+        })
+    }
 }
@@ -0,0 +1,88 @@
+use std::fmt::Debug;
+
+use serde::Deserialize;
+
+/// Constant of reciprocal rank fusion, it dampens the lead of the top results of every engine.
+/// 60 is the value from the original paper.
+const RRF_K: f32 = 60.0;
+
+/// Scores the results returned by an engine.
+///
+/// The score of a result is the sum of the scores it gets from every engine which returned it,
+/// multiplied by the score multiplier of the engine.
+pub trait Ranking: Send + Sync + Debug {
+    /// Score of the result at `rank` (starting at 0 for the top result) of an engine which returned
+    /// `count` results, `longest` is the number of results of the engine which returned the most.
+    fn score(&self, rank: usize, count: usize, longest: usize) -> f32;
+}
+
+/// Scores results by their position from the bottom of the engine's results, relative to the
+/// number of results.
+///
+/// The top result of every engine scores 1, so engines which return few results weigh as much as
+/// the ones which return a lot.
+#[derive(Debug, Default)]
+pub struct PositionRanking;
+
+impl Ranking for PositionRanking {
+    fn score(&self, rank: usize, count: usize, _longest: usize) -> f32 {
+        (count - rank) as f32 / count as f32
+    }
+}
+
+/// Reciprocal rank fusion scores results by `1 / (k + rank)`, favouring results which are
+/// returned by many engines over the ones which are placed high by a single engine.
+#[derive(Debug)]
+pub struct ReciprocalRankFusion {
+    k: f32,
+}
+
+impl ReciprocalRankFusion {
+    pub fn new(k: f32) -> Self {
+        Self { k }
+    }
+}
+
+impl Default for ReciprocalRankFusion {
+    fn default() -> Self {
+        Self::new(RRF_K)
+    }
+}
+
+impl Ranking for ReciprocalRankFusion {
+    fn score(&self, rank: usize, _count: usize, _longest: usize) -> f32 {
+        1.0 / (self.k + rank as f32 + 1.0)
+    }
+}
+
+/// Borda count, a result gets a point for every position it is above the bottom of the longest
+/// list of results. Results at the same rank score the same, however many results an engine
+/// returned.
+#[derive(Debug, Default)]
+pub struct BordaCount;
+
+impl Ranking for BordaCount {
+    fn score(&self, rank: usize, _count: usize, longest: usize) -> f32 {
+        // Normalized so that the top result scores 1, like with the other rankings
+        (longest - rank) as f32 / longest as f32
+    }
+}
+
+/// Rankings which can be selected in the config.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub enum RankingAlgorithm {
+    #[default]
+    Position,
+    ReciprocalRankFusion,
+    Borda,
+}
+
+impl RankingAlgorithm {
+    pub fn build(self) -> Box<dyn Ranking> {
+        match self {
+            RankingAlgorithm::Position => Box::new(PositionRanking),
+            RankingAlgorithm::ReciprocalRankFusion => Box::<ReciprocalRankFusion>::default(),
+            RankingAlgorithm::Borda => Box::new(BordaCount),
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use std::{collections::HashMap, fmt::Debug};
+
+use anyhow::{bail, Result};
+
+use crate::{
+    engines::{
+        bing::Bing, brave::Brave, duckduckgo::DuckDuckGo, google::Google, invidious::Invidious,
+        marginalia::Marginalia, mojeek::Mojeek, selector::SelectorEngine, wikipedia::Wikipedia,
+        Engine,
+    },
+    handler::EngineSettings,
+};
+
+/// Builds an engine from its settings.
+pub type EngineConstructor = Box<dyn Fn(&EngineSettings) -> Result<Box<dyn Engine>> + Send + Sync>;
+
+/// Maps the names used in the config to the engines they create.
+///
+/// The names are matched case insensitively. Engines with a selector definition are always built
+/// as a [`SelectorEngine`] and don't need to be registered.
+pub struct EngineRegistry {
+    // Keyed by the lowercase name, the value holds the name as it was registered
+    constructors: HashMap<String, (String, EngineConstructor)>,
+}
+
+impl EngineRegistry {
+    /// Creates a registry without any engines.
+    pub fn empty() -> Self {
+        Self {
+            constructors: HashMap::new(),
+        }
+    }
+
+    /// Registers an engine, replacing any engine already registered with the same name.
+    pub fn register<F>(&mut self, name: &str, constructor: F)
+    where
+        F: Fn(&EngineSettings) -> Result<Box<dyn Engine>> + Send + Sync + 'static,
+    {
+        self.constructors.insert(
+            name.to_lowercase(),
+            (name.to_string(), Box::new(constructor)),
+        );
+    }
+
+    /// Names of all the registered engines, sorted alphabetically.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self
+            .constructors
+            .values()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Builds the engine called `name`, fails if no engine is registered with that name.
+    pub fn build(&self, name: &str, settings: &EngineSettings) -> Result<Box<dyn Engine>> {
+        if let Some(definition) = &settings.selector {
+            return Ok(Box::new(SelectorEngine::new(name, definition)?));
+        }
+
+        self.check_name(name)?;
+        let (_, constructor) = &self.constructors[&name.to_lowercase()];
+        constructor(settings)
+    }
+
+    /// Fails when no engine is registered under the name.
+    pub fn check_name(&self, name: &str) -> Result<()> {
+        if !self.constructors.contains_key(&name.to_lowercase()) {
+            bail!(
+                "Unknown engine {name}, valid engines are: {}",
+                self.names().join(", ")
+            );
+        }
+        Ok(())
+    }
+}
+
+impl Default for EngineRegistry {
+    /// Creates a registry with all the engines shipped with anvesh.
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        // Add new engines here
+        registry.register("Bing", |_| Ok(Box::new(Bing::new())));
+        registry.register("DuckDuckGo", |_| Ok(Box::new(DuckDuckGo::new())));
+        registry.register("Google", |_| Ok(Box::new(Google::new())));
+        registry.register("Brave", |settings| {
+            Ok(Box::new(Brave::new(settings.api_key.clone())))
+        });
+        registry.register("Mojeek", |_| Ok(Box::new(Mojeek::new())));
+        registry.register("Wikipedia", |_| Ok(Box::new(Wikipedia::new())));
+        registry.register("Invidious", |settings| {
+            Ok(Box::new(Invidious::new(settings.base_url.as_deref())?))
+        });
+        registry.register("Marginalia", |settings| {
+            Ok(Box::new(Marginalia::new(settings.api_key.clone())))
+        });
+        registry
+    }
+}
+
+impl Debug for EngineRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
@@ -0,0 +1,97 @@
+use tracing::instrument;
+
+use crate::{aggregator::Rankable, domain_list::DomainList};
+
+const BLOCKLIST: &str = include_str!("../lists/safe_search_blocklist.txt");
+const ALLOWLIST: &str = include_str!("../lists/safe_search_allowlist.txt");
+
+/// Terms which mark a result as explicit when found as whole words in its title, single words are
+/// also looked for in its host.
+const BLOCKED_TERMS: &[&str] = &["porn", "xxx", "hentai", "nsfw", "nude", "sex cam"];
+
+/// Filters results with the internal lists when safe search is set to `SafeSearchLevel::High`.
+///
+/// Results from blocklisted hosts are always dropped. Results from allowlisted hosts are kept even
+/// if they contain a blocked term, everything else is checked against the blocked terms.
+#[derive(Debug)]
+pub struct SafeSearchFilter {
+    block_list: DomainList,
+    allow_list: DomainList,
+}
+
+impl SafeSearchFilter {
+    pub fn new() -> Self {
+        SafeSearchFilter {
+            block_list: DomainList::parse(BLOCKLIST).expect("Internal blocklist is invalid"),
+            allow_list: DomainList::parse(ALLOWLIST).expect("Internal allowlist is invalid"),
+        }
+    }
+
+    pub fn is_safe(&self, result: &impl Rankable) -> bool {
+        if self.allow_list.is_match(result.page_url()) {
+            return true;
+        }
+        if self.block_list.is_match(result.page_url()) {
+            return false;
+        }
+
+        let host = result.page_url().host_str().unwrap_or_default();
+        let title = words(result.title());
+
+        !BLOCKED_TERMS.iter().any(|term| {
+            let term = words(term);
+            // Hosts are written without spaces, like `freeporn.com`, but matching phrases in them
+            // would catch hosts like `essexcampus.ac.uk`
+            (term.len() == 1 && host.contains(&term[0]))
+                || title.windows(term.len()).any(|words| words == term)
+        })
+    }
+
+    /// Removes all the results which are not safe.
+    #[instrument(level = "TRACE", skip_all)]
+    pub fn filter(&self, results: &mut Vec<impl Rankable>) {
+        let total_results = results.len();
+        results.retain(|result| self.is_safe(result));
+
+        tracing::debug!(
+            "Safe search removed {} results",
+            total_results - results.len()
+        );
+    }
+}
+
+/// Lowercased words of a text.
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SearchResult;
+
+    fn is_safe(url: &str, title: &str) -> bool {
+        let result = SearchResult::new(url, title, "", "Test").unwrap();
+        SafeSearchFilter::new().is_safe(&result)
+    }
+
+    #[test]
+    fn blocked_terms_in_the_host_are_unsafe() {
+        assert!(!is_safe("https://freeporn.example/", "Videos"));
+        assert!(is_safe("https://www.essexcampus.ac.uk/", "Courses"));
+    }
+
+    #[test]
+    fn blocked_terms_must_be_whole_words_in_the_title() {
+        assert!(!is_safe("https://example.com/", "Free NSFW pictures"));
+        assert!(!is_safe("https://example.com/", "Best sex-cam sites"));
+        assert!(is_safe("https://example.com/", "Essex Campus open day"));
+        assert!(is_safe(
+            "https://example.com/",
+            "Pornography laws explained"
+        ));
+    }
+}
@@ -0,0 +1,132 @@
+use lib::engines::{encode_component, UrlBuilder};
+use url::Url;
+
+/// Queries which break a url when they are interpolated as they are.
+const TRICKY_QUERIES: &[&str] = &[
+    "C++",
+    "a&b",
+    "a=b",
+    "#hashtag",
+    "100% cotton",
+    "what is 1+1?",
+    "path/to/file",
+    "emoji 🦀🔍",
+    "مرحبا بالعالم",
+    "שלום עולם",
+    "  leading and trailing  ",
+];
+
+fn query_param(url: &str, key: &str) -> Vec<String> {
+    Url::parse(url)
+        .unwrap()
+        .query_pairs()
+        .filter(|(name, _)| name == key)
+        .map(|(_, value)| value.into_owned())
+        .collect()
+}
+
+#[test]
+fn query_round_trips() {
+    for query in TRICKY_QUERIES {
+        let url = UrlBuilder::new("https://example.com/search")
+            .param("q", query)
+            .param("page", 2)
+            .build();
+
+        assert_eq!(query_param(&url, "q"), [*query], "{url}");
+        assert_eq!(query_param(&url, "page"), ["2"], "{url}");
+    }
+}
+
+#[test]
+fn query_cant_inject_params() {
+    let url = UrlBuilder::new("https://example.com/search")
+        .param("q", "a&page=5#top")
+        .build();
+
+    assert!(query_param(&url, "page").is_empty(), "{url}");
+    assert_eq!(Url::parse(&url).unwrap().fragment(), None);
+}
+
+#[test]
+fn plus_is_not_a_space() {
+    let url = UrlBuilder::new("https://example.com/search")
+        .param("q", "C++ vs c")
+        .build();
+
+    assert_eq!(url, "https://example.com/search?q=C%2B%2B+vs+c");
+}
+
+#[test]
+fn base_url_params_are_kept() {
+    let url = UrlBuilder::new("https://example.com/search?source=web")
+        .param("q", "a&b")
+        .build();
+
+    assert_eq!(url, "https://example.com/search?source=web&q=a%26b");
+}
+
+#[test]
+fn path_segments_are_encoded() {
+    for query in TRICKY_QUERIES {
+        let url = UrlBuilder::new("https://example.com/")
+            .path(["search", query])
+            .build();
+
+        let url = Url::parse(&url).unwrap();
+        assert_eq!(url.query(), None, "{url}");
+        assert_eq!(url.fragment(), None, "{url}");
+
+        let segments: Vec<_> = url.path_segments().unwrap().collect();
+        assert_eq!(segments.len(), 2, "{url}");
+        assert_eq!(segments[0], "search");
+        assert_eq!(
+            percent_decode(segments[1]),
+            *query,
+            "{url} doesn't round trip"
+        );
+    }
+}
+
+#[test]
+fn path_is_appended_to_base() {
+    let base = Url::parse("https://invidious.example.com/instance/").unwrap();
+    let url = UrlBuilder::from(base)
+        .path(["api", "v1", "search"])
+        .param("q", "🦀")
+        .build();
+
+    assert_eq!(
+        url,
+        "https://invidious.example.com/instance/api/v1/search?q=%F0%9F%A6%80"
+    );
+}
+
+#[test]
+fn encoded_components_round_trip() {
+    for query in TRICKY_QUERIES {
+        let url = format!(
+            "https://example.com/search?q={}&page=2",
+            encode_component(query)
+        );
+
+        assert_eq!(query_param(&url, "q"), [*query], "{url}");
+        assert_eq!(query_param(&url, "page"), ["2"], "{url}");
+    }
+}
+
+fn percent_decode(segment: &str) -> String {
+    let mut bytes = Vec::new();
+    let mut rest = segment.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(&tail[..2]).unwrap();
+            bytes.push(u8::from_str_radix(hex, 16).unwrap());
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).unwrap()
+}
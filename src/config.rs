@@ -2,7 +2,8 @@ use std::collections::HashMap;
 use std::fs::File;
 use std::path::Path;
 
-use anyhow::Result;
+use anyhow::{ensure, Result};
+use lib::{host_rules::HostRule, ranking::RankingAlgorithm, SelectorDefinition};
 use serde::Deserialize;
 use serde_yaml::from_reader;
 
@@ -13,6 +14,11 @@ pub struct Config {
     pub log_level: u8,
     /// Maximum threads to be used by the internal tokio scheduler.
     pub threads: usize,
+    /// Maximum threads to be used for blocking operations. Uses tokio's default when not set.
+    pub blocking_threads: Option<usize>,
+    /// Name given to the threads spawned by the tokio scheduler.
+    #[serde(default = "default_thread_name")]
+    pub thread_name: String,
     /// Port to run the web server on.
     pub port: u16,
     /// The interface to bind the web server to.
@@ -25,6 +31,15 @@ pub struct Config {
     pub user_agents: Vec<String>,
     /// Whole numbers from 0 to 3. 0 corresponds to no filtering, 1 to low, etc.
     pub safe_search_level: u8,
+    /// Algorithm used to rank the results of all the engines.
+    #[serde(default)]
+    pub ranking: RankingAlgorithm,
+    /// Collapse results linking to copies of the same page, like AMP pages and mirrors.
+    #[serde(default)]
+    pub collapse_near_duplicates: bool,
+    /// Rules to rewrite, boost, penalize or hide the results from matching hosts.
+    #[serde(default)]
+    pub host_rules: Vec<HostRule>,
     /// Configuration for proxy
     pub proxy: Option<ProxyConfig>,
     /// Specific upstream engine settings.
@@ -47,18 +62,46 @@ pub struct ProxyConfig {
 #[derive(Debug, Deserialize)]
 pub struct RateLimiter {
     pub number_of_requests: usize,
+    /// Time (in seconds) within which `number_of_requests` are allowed.
     pub time_limit: u64,
+    /// Use the client address from `X-Forwarded-For`. Only enable this when behind a reverse proxy.
+    #[serde(default)]
+    pub trust_forwarded_for: bool,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct EngineConfig {
     pub enabled: bool,
-    pub timeout: u128,
+    /// Maximum time (in milliseconds) the engine is allowed to take for a search.
+    pub timeout: u64,
     pub score_multiplier: f32,
+    /// Key for engines which can be queried through an official API.
+    pub api_key: Option<String>,
+    /// Instance to query for engines which are front-ends, like Invidious.
+    pub base_url: Option<String>,
+    /// Defines an engine scraped with css selectors, the name of the engine can be anything.
+    pub selector: Option<SelectorDefinition>,
+}
+
+fn default_thread_name() -> String {
+    "anvesh-worker".to_string()
+}
+
+impl Config {
+    /// Checks for values which are accepted by the parser but can't be used.
+    pub fn validate(&self) -> Result<()> {
+        ensure!(self.threads > 0, "threads must be greater than 0");
+        ensure!(
+            self.blocking_threads != Some(0),
+            "blocking_threads must be greater than 0"
+        );
+        Ok(())
+    }
 }
 
 pub fn parse_config(path: impl AsRef<Path>) -> Result<Config> {
     let file = File::open(path)?;
     let config: Config = from_reader(file)?;
+    config.validate()?;
     Ok(config)
 }
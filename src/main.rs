@@ -1,26 +1,32 @@
 pub mod cli;
 pub mod config;
+pub mod rate_limiter;
 pub mod server;
 pub mod templates;
 
-use std::sync::Arc;
+use std::{collections::HashMap, net::SocketAddr, sync::Arc, time::Duration};
 
-use lib::Handler;
+use lib::{
+    domain_list::DomainList, handler::EngineSettings, host_rules::HostRules,
+    registry::EngineRegistry, Handler, HandlerConfig, SafeSearchLevel,
+};
 
 use clap::Parser;
 
-use axum::{routing::get, Router};
-use config::parse_config;
+use axum::{middleware, routing::get, Router};
+use config::{parse_config, Config};
 
 use tracing::level_filters::LevelFilter;
 use tracing_subscriber::{fmt::format::FmtSpan, FmtSubscriber};
 
-use crate::server::{index_handler, search_handler};
+use crate::{
+    rate_limiter::{rate_limit, RateLimiter},
+    server::{index_handler, search_handler},
+};
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
+fn main() -> anyhow::Result<()> {
     let parsed_args = cli::Cli::parse();
-    let pconfig = parse_config(parsed_args.config_path).unwrap();
+    let pconfig = parse_config(&parsed_args.config_path)?;
 
     let log_level = match pconfig.log_level {
         0 => LevelFilter::TRACE,
@@ -38,46 +44,111 @@ async fn main() -> anyhow::Result<()> {
 
     tracing::subscriber::set_global_default(logger).expect("Setting default logger failed");
 
+    if pconfig.threads > num_cpus::get() {
+        tracing::warn!(
+            "{} threads were configured but only {} cpus are available",
+            pconfig.threads,
+            num_cpus::get()
+        );
+    }
+
+    let mut runtime = tokio::runtime::Builder::new_multi_thread();
+    runtime
+        .worker_threads(pconfig.threads)
+        .thread_name(&pconfig.thread_name)
+        .enable_all();
+    if let Some(blocking_threads) = pconfig.blocking_threads {
+        runtime.max_blocking_threads(blocking_threads);
+    }
+
+    runtime.build()?.block_on(run(parsed_args, pconfig))
+}
+
+async fn run(parsed_args: cli::Cli, pconfig: Config) -> anyhow::Result<()> {
     let score_multiplers = pconfig
         .upstream_search_engines
         .iter()
         .map(|(key, conf)| (key.clone(), conf.score_multiplier))
         .collect();
 
+    let registry = EngineRegistry::default();
+    // Disabled engines are checked as well, so that typos don't go unnoticed
+    for (key, conf) in &pconfig.upstream_search_engines {
+        if conf.selector.is_none() {
+            registry.check_name(key)?;
+        }
+    }
+
     let engines = pconfig
         .upstream_search_engines
-        .keys()
-        .cloned()
-        .collect::<Vec<String>>();
-
-    let backend_handler = match pconfig.proxy {
-        Some(ref proxy) => {
-            Handler::new(
-                score_multiplers,
-                pconfig.request_timeout,
-                Some(&proxy.connection_url),
-                Some(proxy.is_tor),
-                &engines,
-                pconfig.user_agents,
-            )
-            .await?
-        }
-        None => {
-            Handler::new(
-                score_multiplers,
-                pconfig.request_timeout,
-                None,
-                None,
-                &engines,
-                pconfig.user_agents,
-            )
-            .await?
-        }
+        .iter()
+        .filter(|(key, conf)| {
+            if !conf.enabled {
+                tracing::info!("{key} is disabled, skipping");
+            }
+            conf.enabled
+        })
+        .map(|(key, conf)| {
+            let settings = EngineSettings {
+                timeout: Duration::from_millis(conf.timeout),
+                api_key: conf.api_key.clone(),
+                base_url: conf.base_url.clone(),
+                selector: conf.selector.clone(),
+            };
+            (key.clone(), settings)
+        })
+        .collect::<HashMap<String, EngineSettings>>();
+
+    let safe_search_level = SafeSearchLevel::try_from(pconfig.safe_search_level)?;
+
+    let allow_list = match parsed_args.allow_list {
+        Some(ref path) => DomainList::from_file(path)?,
+        None => DomainList::default(),
+    };
+    let block_list = match parsed_args.block_list {
+        Some(ref path) => DomainList::from_file(path)?,
+        None => DomainList::default(),
     };
+    tracing::info!(
+        "Loaded {} allow list and {} block list entries",
+        allow_list.len(),
+        block_list.len()
+    );
+
+    let host_rules = HostRules::new(pconfig.host_rules)?;
+    tracing::info!("Loaded {} host rules", host_rules.len());
+
+    tracing::info!("Ranking results with {:?}", pconfig.ranking);
+
+    let backend_handler = Handler::new(HandlerConfig {
+        ranking: pconfig.ranking.build(),
+        engine_score_multipliers: score_multiplers,
+        timeout: pconfig.request_timeout,
+        proxy_url: pconfig
+            .proxy
+            .as_ref()
+            .map(|proxy| proxy.connection_url.clone()),
+        is_tor: pconfig.proxy.as_ref().map(|proxy| proxy.is_tor),
+        engines,
+        registry,
+        user_agents: pconfig.user_agents,
+        safe_search_level,
+        allow_list,
+        block_list,
+        collapse_near_duplicates: pconfig.collapse_near_duplicates,
+        host_rules,
+    })
+    .await?;
+
+    let rate_limiter = Arc::new(RateLimiter::new(&pconfig.rate_limiter)?);
 
     let app = Router::new()
         .route("/", get(index_handler))
-        .route("/search", get(search_handler))
+        .route(
+            "/search",
+            get(search_handler)
+                .route_layer(middleware::from_fn_with_state(rate_limiter, rate_limit)),
+        )
         .with_state(Arc::new(backend_handler));
     let listener = tokio::net::TcpListener::bind((pconfig.bind_ip.clone(), pconfig.port))
         .await
@@ -88,7 +159,12 @@ async fn main() -> anyhow::Result<()> {
         pconfig.bind_ip,
         pconfig.port
     );
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .unwrap();
 
     Ok(())
 }
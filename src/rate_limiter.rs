@@ -0,0 +1,158 @@
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::{ensure, Result};
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{
+        header::{ACCEPT, RETRY_AFTER},
+        HeaderMap, StatusCode,
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::{config, templates::RateLimitedTemplate};
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// A token bucket rate limiter keyed by the client's IP address.
+///
+/// Every client gets a bucket holding `number_of_requests` tokens which refills completely over
+/// `time_limit` seconds. Each request takes a token and is rejected when the bucket is empty.
+#[derive(Debug)]
+pub struct RateLimiter {
+    capacity: f64,
+    // Tokens added per second
+    refill_rate: f64,
+    trust_forwarded_for: bool,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+    last_prune: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(config: &config::RateLimiter) -> Result<Self> {
+        ensure!(
+            config.number_of_requests > 0 && config.time_limit > 0,
+            "The rate limiter's number_of_requests and time_limit must be greater than 0"
+        );
+
+        Ok(RateLimiter {
+            capacity: config.number_of_requests as f64,
+            refill_rate: config.number_of_requests as f64 / config.time_limit as f64,
+            trust_forwarded_for: config.trust_forwarded_for,
+            buckets: Mutex::new(HashMap::new()),
+            last_prune: Mutex::new(Instant::now()),
+        })
+    }
+
+    /// Takes a token from the client's bucket.
+    ///
+    /// Returns the time after which the client can retry if the bucket is empty.
+    fn acquire(&self, client: IpAddr) -> Result<(), Duration> {
+        let now = Instant::now();
+        self.prune(now);
+
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets.entry(client).or_insert(Bucket {
+            tokens: self.capacity,
+            last_refill: now,
+        });
+
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.refill_rate).min(self.capacity);
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - bucket.tokens) / self.refill_rate,
+            ))
+        }
+    }
+
+    /// Removes the buckets which would have been refilled completely by now, as they are
+    /// equivalent to a new bucket.
+    fn prune(&self, now: Instant) {
+        let refill_time = Duration::from_secs_f64(self.capacity / self.refill_rate);
+
+        let mut last_prune = self.last_prune.lock().unwrap();
+        if now.duration_since(*last_prune) < refill_time {
+            return;
+        }
+        *last_prune = now;
+
+        self.buckets
+            .lock()
+            .unwrap()
+            .retain(|_, bucket| now.duration_since(bucket.last_refill) < refill_time);
+    }
+
+    /// Determines the IP address of the client.
+    ///
+    /// When the server is behind a reverse proxy, the last address in `X-Forwarded-For` is the one
+    /// added by the proxy. The addresses before it are set by the client and can't be trusted.
+    fn client_ip(&self, peer: SocketAddr, headers: &HeaderMap) -> IpAddr {
+        if self.trust_forwarded_for {
+            let forwarded_ip = headers
+                .get_all("X-Forwarded-For")
+                .iter()
+                .next_back()
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.rsplit(',').next())
+                .and_then(|ip| ip.trim().parse().ok());
+
+            if let Some(ip) = forwarded_ip {
+                return ip;
+            }
+            tracing::debug!("X-Forwarded-For is missing or malformed, using peer address");
+        }
+        peer.ip()
+    }
+}
+
+/// Middleware which rejects clients that have exceeded their rate limit.
+pub async fn rate_limit(
+    State(limiter): State<Arc<RateLimiter>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let client = limiter.client_ip(peer, request.headers());
+
+    let retry_after = match limiter.acquire(client) {
+        Ok(()) => return next.run(request).await,
+        Err(retry_after) => retry_after.as_secs_f64().ceil() as u64,
+    };
+
+    tracing::debug!("Rate limited {client} for {retry_after} seconds");
+
+    let wants_html = request
+        .headers()
+        .get(ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .map(|accept| accept.contains("text/html"))
+        .unwrap_or(false);
+
+    let mut response = if wants_html {
+        RateLimitedTemplate { retry_after }.into_response()
+    } else {
+        format!("Too many requests, retry after {retry_after} seconds").into_response()
+    };
+    *response.status_mut() = StatusCode::TOO_MANY_REQUESTS;
+    response
+        .headers_mut()
+        .insert(RETRY_AFTER, retry_after.to_string().parse().unwrap());
+
+    response
+}
@@ -2,10 +2,11 @@ use std::sync::Arc;
 
 use axum::{
     extract::{Query, State},
+    http::{header, HeaderMap},
     response::{IntoResponse, Response},
     Json,
 };
-use lib::{Handler, Relavancy, SafeSearchLevel};
+use lib::{Category, Handler, Locale, Relavancy, SafeSearchLevel, SearchOptions, SortOrder};
 use serde::Deserialize;
 
 use crate::templates::{IndexTemplate, SearchTemplate};
@@ -16,6 +17,10 @@ pub struct SearchParams {
     page: Option<u16>,
     relavancy: Option<Relavancy>,
     safe_level: Option<SafeSearchLevel>,
+    category: Option<Category>,
+    sort: Option<SortOrder>,
+    // Language tag like `en-US`
+    language: Option<String>,
     json: Option<bool>,
 }
 
@@ -23,18 +28,49 @@ pub async fn index_handler() -> IndexTemplate {
     IndexTemplate
 }
 
+/// Picks the locale from the query, the `language` preference cookie or the `Accept-Language`
+/// header, in that order.
+fn request_locale(params: &SearchParams, headers: &HeaderMap) -> Option<Locale> {
+    let from_query = params
+        .language
+        .as_deref()
+        .and_then(|language| language.parse().ok());
+
+    let from_cookie = || {
+        headers
+            .get_all(header::COOKIE)
+            .iter()
+            .filter_map(|cookies| cookies.to_str().ok())
+            .flat_map(|cookies| cookies.split(';'))
+            .filter_map(|cookie| cookie.trim().split_once('='))
+            .find(|(name, _)| *name == "language")
+            .and_then(|(_, language)| language.parse().ok())
+    };
+
+    let from_header = || {
+        headers
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|header| header.to_str().ok())
+            .and_then(Locale::from_accept_language)
+    };
+
+    from_query.or_else(from_cookie).or_else(from_header)
+}
+
 pub async fn search_handler(
     Query(params): Query<SearchParams>,
     State(backend): State<Arc<Handler>>,
+    headers: HeaderMap,
 ) -> Response {
-    let result = backend
-        .search(
-            params.query,
-            params.page.unwrap_or(0),
-            params.relavancy,
-            params.safe_level,
-        )
-        .await;
+    let options = SearchOptions {
+        page: params.page.unwrap_or(0),
+        relavancy: params.relavancy,
+        safe_level: params.safe_level,
+        category: params.category.unwrap_or_default(),
+        sort: params.sort.unwrap_or_default(),
+        locale: request_locale(&params, &headers),
+    };
+    let result = backend.search(params.query, options).await;
 
     if params.json.unwrap_or(false) {
         Json(result).into_response()
@@ -1,15 +1,29 @@
 use askama_axum::Template;
-use lib::{errors::EngineError, QueryResult, SearchResult};
+use lib::{
+    errors::EngineError, Category, ImageResult, Infobox, NewsResult, QueryResult, SearchResult,
+    VideoResult,
+};
 
 #[derive(Template)]
 #[template(path = "base.html")]
 pub struct IndexTemplate;
 
+#[derive(Template)]
+#[template(path = "ratelimited.html")]
+pub struct RateLimitedTemplate {
+    pub retry_after: u64,
+}
+
 #[derive(Template)]
 #[template(path = "search.html")]
 pub struct SearchTemplate {
     pub query: String,
+    pub category: Category,
     pub results: Vec<SearchResult>,
+    pub images: Vec<ImageResult>,
+    pub news: Vec<NewsResult>,
+    pub videos: Vec<VideoResult>,
+    pub infobox: Option<Infobox>,
     pub errors: Vec<EngineError>,
 }
 
@@ -17,7 +31,12 @@ impl SearchTemplate {
     pub fn new(result: QueryResult) -> SearchTemplate {
         SearchTemplate {
             query: result.query,
+            category: result.category,
             results: result.results,
+            images: result.images,
+            news: result.news,
+            videos: result.videos,
+            infobox: result.infobox,
             errors: result.errors,
         }
     }